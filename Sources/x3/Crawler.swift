@@ -90,11 +90,24 @@ struct Crawler {
         /// directly to the right of the current node, and pick its selected
         /// leaf node.
         case selected
-        // TODO: nearest
+
+        /// Always pick the first child of each container.
+        case first
+
+        /// Pick the child nearest to where we came from.
+        ///
+        /// For example, `move(.right, leaf: .nearest)` picks the leftmost child
+        /// of any horizontal container it descends into. Containers split the
+        /// other way, as well as tabbed and stacked containers, follow their
+        /// selection.
+        case nearest
     }
 
     /// Moves the crawler in the cardinal direction specified.
     ///
+    /// Tabbed and stacked containers are treated as a single unit: the crawler
+    /// moves past them instead of between their children.
+    ///
     /// Selects a leaf node according to the requested `DescentStrategy`.
     func move(_ direction: Direction, leaf: DescentStrategy) -> Crawler? {
         // Move in the desired direction.
@@ -104,13 +117,29 @@ struct Crawler {
 
         // Now descend the tree.
         var child = newContainer.children[index]
+        while let next = descendOne(child, direction, leaf) {
+            child = next
+        }
+        return Crawler(at: child)
+    }
+
+    private func descendOne(_ node: NodeKind, _ direction: Direction,
+                            _ leaf: DescentStrategy) -> NodeKind? {
+        guard let container = node.containerNode else {
+            return nil
+        }
         switch leaf {
         case .selected:
-            while let selection = child.selection {
-                child = selection
+            return container.selection
+        case .first:
+            return container.children.first
+        case .nearest:
+            if container.layout.isProportional &&
+                container.layout.orientation == direction.orientation {
+                return (direction.value > 0) ? container.children.first : container.children.last
             }
+            return container.selection
         }
-        return Crawler(at: child)
     }
 }
 
@@ -124,8 +153,12 @@ fileprivate func moveOne(_ node: NodeKind, _ direction: Direction, cursor: Bool)
     }
 
     // Walk up the tree until we're able to move in the right direction (or hit the end).
+    // A cursor doesn't move between the children of tabbed and stacked containers.
     let movingNode = cursor ? nil : node
-    while container != nil && !canMove(direction, from: child, movingNode) {
+    while container != nil && (
+        !canMove(direction, from: child, movingNode) ||
+        (cursor && !container!.layout.isProportional)
+    ) {
         child     = NodeKind.container(container!)
         container = container!.parent
     }
@@ -171,13 +204,7 @@ extension NodeKind {
         } else {
             // We couldn't find a move in this direction.
 
-            // If we're already in a the root which has same orientation of the
-            // desired move, or we're the only child, then we just "hit the end"
-            // and don't do anything.
-            if parent.parent == nil && (
-                parent.layout.orientation == direction.orientation ||
-                parent.children.count == 1
-            ) {
+            if hitsEnd(parent, direction) {
                 return
             }
 
@@ -192,6 +219,27 @@ extension NodeKind {
         }
     }
 
+    /// Whether `move(inDirection:)` would change the tree.
+    func canMove(inDirection direction: Direction) -> Bool {
+        guard let parent = parent else {
+            return false
+        }
+        if getMoveDestination(from: self, direction) != nil {
+            return true
+        }
+        return !hitsEnd(parent, direction)
+    }
+
+    // If we're already in a the root which has same orientation of the
+    // desired move, or we're the only child, then we just "hit the end"
+    // and don't do anything.
+    private func hitsEnd(_ parent: ContainerNode, _ direction: Direction) -> Bool {
+        return parent.parent == nil && (
+            parent.layout.orientation == direction.orientation ||
+            parent.children.count == 1
+        )
+    }
+
     private func getMoveDestination(from node: NodeKind,
                                     _ direction: Direction) -> (ContainerNode, InsertionPolicy)? {
         // Move in the desired direction.
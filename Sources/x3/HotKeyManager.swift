@@ -3,13 +3,25 @@ import Carbon
 
 private let hotKeySignature = fourCharCodeFrom("X3WM")
 
+/// A registered hotkey and the command it runs.
+public struct HotKeyBinding: Codable, Equatable {
+    public let keyCode: Int
+    public let modifiers: [String]
+    public let command: String
+}
+
 public class HotKeyManager {
     private var handlers: [() -> ()] = []
+    public private(set) var bindings: [HotKeyBinding] = []
 
     public init() {}
 
-    func register(keyCode: Int, modifierKeys: Int, handler: @escaping () -> ()) {
+    func register(keyCode: Int, modifierKeys: Int, command: String,
+                  handler: @escaping () -> ()) {
         handlers.append(handler)
+        bindings.append(HotKeyBinding(keyCode: keyCode,
+                                      modifiers: modifierNames(modifierKeys),
+                                      command: command))
 
         var hotKeyID = EventHotKeyID()
         hotKeyID.signature = hotKeySignature
@@ -30,6 +42,11 @@ public class HotKeyManager {
             GetApplicationEventTarget(), 0, &hotKeyRef)
     }
 
+    /// Returns the registered bindings as JSON.
+    public func exportBindings() throws -> Data {
+        return try JSONEncoder().encode(bindings)
+    }
+
     static func handleCarbonEvent(_ event: EventRef?, _ userData: UnsafeMutableRawPointer?)
     -> OSStatus {
         guard let event = event else {
@@ -73,3 +90,8 @@ private func fourCharCodeFrom(_ string : String) -> FourCharCode {
     }
     return result
 }
+
+private func modifierNames(_ modifierKeys: Int) -> [String] {
+    let names = [(controlKey, "control"), (optionKey, "option"), (shiftKey, "shift"), (cmdKey, "cmd")]
+    return names.filter { modifierKeys & $0.0 != 0 }.map { $0.1 }
+}
@@ -18,6 +18,16 @@ extension Layout {
             case .tabbed: return false
         }
     }
+
+    /// The equivalent layout in the opposite orientation.
+    var flipped: Layout {
+        switch self {
+            case .horizontal: return .vertical
+            case .vertical: return .horizontal
+            case .stacked: return .tabbed
+            case .tabbed: return .stacked
+        }
+    }
 }
 
 let WINDOWS = CodingUserInfoKey(rawValue: "windows")!
@@ -37,6 +47,13 @@ final class Tree {
     fileprivate(set) var root: ContainerNode
     var screen: Swindler.Screen! = nil
 
+    /// Space to leave empty at the edges of the screen, for things like a
+    /// third-party status bar or dock.
+    ///
+    /// This is in addition to the system menu bar and Dock, which are already
+    /// excluded by the screen's applicationFrame.
+    var reserved = NSEdgeInsets()
+
     init(screen: Swindler.Screen) {
         self.root = ContainerNode(.horizontal, parent: nil)
         setup(screen)
@@ -62,15 +79,139 @@ final class Tree {
         return root.find(window: window)
     }
 
+    /// Computes the frame of every window in the tree, without applying them.
+    func calculateLayout() -> [(WindowNode, CGRect)] {
+        var frames: [(WindowNode, CGRect)] = []
+        root.delegate.calculateLayout_(layoutFrame, &frames)
+        return frames
+    }
+
+    /// Computes the frame of a single node.
+    ///
+    /// Only the path from the root to the node is visited, so this is cheaper
+    /// than `calculateLayout` when a single frame is needed. Returns nil if the
+    /// node is not in this tree.
+    func frame(of node: Node) -> CGRect? {
+        var path: [Node] = []
+        var current: Node? = node
+        while let n = current {
+            path.append(n)
+            current = n.parent
+        }
+        guard path.last === root else {
+            return nil
+        }
+        var rect = layoutFrame
+        for (parent, child) in zip(path.reversed(), path.reversed().dropFirst()) {
+            rect = (parent as! ContainerNode).rectForChild(child, whole: rect)
+        }
+        return rect.rounded()
+    }
+
+    /// The area of the screen windows are laid out in.
+    private var layoutFrame: CGRect {
+        let frame = screen.applicationFrame
+        return CGRect(x: frame.minX + reserved.left,
+                      y: frame.minY + reserved.bottom,
+                      width: frame.width - reserved.left - reserved.right,
+                      height: frame.height - reserved.top - reserved.bottom)
+    }
+
     func refresh() {
-        var promises: [Promise<()>]? = nil
-        root.delegate.refresh_(screen.applicationFrame, &promises)
+        for (node, rect) in calculateLayout() {
+            _ = node.window.frame.set(rect)
+        }
     }
 
     func awaitRefresh() -> Promise<()> {
-        var promises: [Promise<()>]? = []
-        root.delegate.refresh_(screen.applicationFrame, &promises)
-        return when(fulfilled: promises!)
+        return when(fulfilled: calculateLayout().map { (node, rect) in
+            node.window.frame.set(rect).map({_ in ()})
+        })
+    }
+}
+
+/// The frames of a set of windows, used to compare layouts.
+struct LayoutSnapshot {
+    let frames: [(Swindler.Window, CGRect)]
+
+    init(_ frames: [(Swindler.Window, CGRect)]) {
+        self.frames = frames
+    }
+
+    func frame(of window: Swindler.Window) -> CGRect? {
+        return frames.first(where: { $0.0 == window })?.1
+    }
+
+    /// Returns the windows whose frames differ from those in `other`,
+    /// including windows that only appear in one of the two snapshots.
+    func diff(_ other: LayoutSnapshot) -> [Swindler.Window] {
+        let changed = frames.filter { (window, frame) in other.frame(of: window) != frame }
+        let added = other.frames.filter { (window, _) in self.frame(of: window) == nil }
+        return (changed + added).map { $0.0 }
+    }
+}
+
+extension Tree {
+    func snapshot() -> LayoutSnapshot {
+        return LayoutSnapshot(calculateLayout().map { (node, rect) in (node.window, rect) })
+    }
+}
+
+extension Tree {
+    /// Removes all containers except the root, leaving every window as a direct
+    /// child of the root in its current order. The root becomes horizontal and
+    /// all windows get equal sizes.
+    func flatten() {
+        for window in root.kind.windows {
+            window.reparent(root, at: .end)
+        }
+        // reparent only culls a window's immediate parent. cullIfEmpty doesn't
+        // recurse into ancestors, so containers that held only containers are
+        // left behind, empty.
+        for case .container(let container) in root.children {
+            container.destroyAll()
+        }
+        root.layout = .horizontal
+        root.wmData.unstackLayout = nil
+        for child in root.children {
+            child.base.size = 1.0 / Float(root.children.count)
+        }
+    }
+}
+
+extension Tree {
+    /// Exchanges the positions and sizes of two windows, even if they are in
+    /// different containers.
+    ///
+    /// Within a container, the selection follows the selected window to its new
+    /// place. Across containers, each container keeps its selected slot, which
+    /// now holds the window that was swapped in.
+    ///
+    /// Returns false, leaving the tree unchanged, if either window isn't in the
+    /// tree.
+    @discardableResult
+    func swap(_ a: Swindler.Window, _ b: Swindler.Window) -> Bool {
+        guard let aNode = find(window: a), let bNode = find(window: b) else {
+            return false
+        }
+        if aNode == bNode {
+            return true
+        }
+        let aParent = aNode.parent!
+        let bParent = bNode.parent!
+        if aParent == bParent {
+            aParent.swapChildren(aNode.kind, bNode.kind)
+        } else {
+            // Find both slots before writing to either.
+            let aIndex = aParent.children.firstIndex(of: aNode.kind)!
+            let bIndex = bParent.children.firstIndex(of: bNode.kind)!
+            aParent.replaceChild(at: aIndex, with: bNode.kind)
+            bParent.replaceChild(at: bIndex, with: aNode.kind)
+            aNode.parent = bParent
+            bNode.parent = aParent
+        }
+        (aNode.size, bNode.size) = (bNode.size, aNode.size)
+        return true
     }
 }
 
@@ -82,7 +223,10 @@ extension Tree: Codable {
 
 class Node: Codable {
     fileprivate(set) var parent: ContainerNode?
-    fileprivate var size: Float32
+    fileprivate(set) var size: Float32
+
+    /// Whether this node keeps its size when siblings are added or removed.
+    var sizeLocked: Bool = false
     private weak var delegate_: NodeDelegate?
     fileprivate var delegate: NodeDelegate {
         get {
@@ -96,7 +240,7 @@ class Node: Codable {
     }
 
     enum CodingKeys: CodingKey {
-        case size
+        case size, sizeLocked
     }
 
     fileprivate init(parent: ContainerNode?) {
@@ -104,6 +248,18 @@ class Node: Codable {
         self.size = 0.0
     }
 
+    required init(from decoder: Decoder) throws {
+        let object = try decoder.container(keyedBy: CodingKeys.self)
+        size = try object.decode(Float32.self, forKey: .size)
+        sizeLocked = try object.decodeIfPresent(Bool.self, forKey: .sizeLocked) ?? false
+    }
+
+    func encode(to encoder: Encoder) throws {
+        var object = encoder.container(keyedBy: CodingKeys.self)
+        try object.encode(size, forKey: .size)
+        try object.encode(sizeLocked, forKey: .sizeLocked)
+    }
+
     var kind: NodeKind {
         delegate.getKind()
     }
@@ -174,21 +330,41 @@ extension Node {
     func contains(window: Swindler.Window) -> Bool {
         return self.kind.find(window: window) != nil
     }
+
+    /// The number of ancestors of this node. The root has a depth of 0.
+    var depth: Int {
+        var depth = 0
+        var ancestor = parent
+        while let cur = ancestor {
+            depth += 1
+            ancestor = cur.parent
+        }
+        return depth
+    }
+
+    /// The number of levels between this node and its deepest descendant.
+    /// Windows and empty containers have a height of 0.
+    var height: Int {
+        guard case .container(let container) = kind else {
+            return 0
+        }
+        return container.children.map { $0.base.height + 1 }.max() ?? 0
+    }
 }
 
 extension Node {
     func find(window: Swindler.Window) -> WindowNode? {
         delegate.find_(window)
     }
-    fileprivate func refresh(rect: CGRect, _ promises: inout [Promise<()>]?) {
-        delegate.refresh_(rect, &promises)
+    fileprivate func calculateLayout(rect: CGRect, _ frames: inout [(WindowNode, CGRect)]) {
+        delegate.calculateLayout_(rect, &frames)
     }
 }
 
 fileprivate protocol NodeDelegate: AnyObject {
     func getKind() -> NodeKind
     func find_(_: Swindler.Window) -> WindowNode?
-    func refresh_(_: CGRect, _: inout [Promise<()>]?)
+    func calculateLayout_(_: CGRect, _: inout [(WindowNode, CGRect)])
 }
 
 enum NodeKind {
@@ -230,8 +406,20 @@ extension NodeKind {
     func find(window: Swindler.Window) -> WindowNode? {
         self.base.find(window: window)
     }
-    fileprivate func refresh(rect: CGRect, _ promises: inout [Promise<()>]?) {
-        self.base.refresh(rect: rect, &promises)
+    fileprivate func calculateLayout(rect: CGRect, _ frames: inout [(WindowNode, CGRect)]) {
+        self.base.calculateLayout(rect: rect, &frames)
+    }
+}
+
+extension NodeKind {
+    /// All windows in this subtree, in order.
+    var windows: [WindowNode] {
+        switch self {
+        case .container(let node):
+            return node.children.flatMap { $0.windows }
+        case .window(let node):
+            return [node]
+        }
     }
 }
 
@@ -445,6 +633,19 @@ extension ContainerNode {
         return child
     }
 
+    /// Creates a container, lets `f` fill it, then sizes its children in
+    /// proportion to `weights`.
+    @discardableResult
+    func createContainer(layout: Layout,
+                         at: InsertionPolicy,
+                         weights: [Float],
+                         _ f: (ContainerNode) -> ())
+    -> ContainerNode {
+        let child = createContainer(layout: layout, at: at, f)
+        child.setWeights(weights)
+        return child
+    }
+
     @discardableResult
     func createWindow(_ window: Swindler.Window, at: InsertionPolicy) -> WindowNode {
         let node = WindowNode(window, parent: self)
@@ -461,6 +662,40 @@ extension ContainerNode {
         onNewNode(index: index)
     }
 
+    /// Moves `child` to position `index` among our children, shifting the
+    /// others over. Sizes and the selected node are preserved.
+    ///
+    /// If `child` belongs to another container, it is reparented instead, which
+    /// adjusts sizes in both containers as usual.
+    func moveChild(_ child: NodeKind, to index: Int) {
+        guard child.parent == self else {
+            child.node.reparent(self, at: .at(min(index, children.count)))
+            return
+        }
+        let selected = selection
+        children.remove(at: children.firstIndex(of: child)!)
+        children.insert(child, at: min(index, children.count))
+        if let selected = selected {
+            selectionData = children.firstIndex(of: selected)!
+        }
+    }
+
+    /// Puts `new` at `index`, in place of the child that was there. Neither
+    /// node's parent is updated.
+    fileprivate func replaceChild(at index: Int, with new: NodeKind) {
+        children[index] = new
+    }
+
+    /// Exchanges the places of two of our children. The selected node stays
+    /// selected.
+    fileprivate func swapChildren(_ a: NodeKind, _ b: NodeKind) {
+        let selected = selection
+        children.swapAt(children.firstIndex(of: a)!, children.firstIndex(of: b)!)
+        if let selected = selected {
+            selectionData = children.firstIndex(of: selected)!
+        }
+    }
+
     private func indexForPolicy(_ policy: InsertionPolicy) -> Int {
         switch policy {
         case .begin:
@@ -489,7 +724,7 @@ extension ContainerNode {
         }
         children.remove(at: index)
 
-        onRemoveNode()
+        onRemoveNode(index: index)
     }
 
     fileprivate func removeChild(_ node: NodeKind) {
@@ -511,8 +746,9 @@ extension ContainerNode {
         onNewNodeUpdateSelection(index: index)
     }
 
-    private func onRemoveNode() {
+    private func onRemoveNode(index: Int) {
         onRemoveNodeAdjustSize()
+        onRemoveNodeUpdateSelection(index: index)
     }
 }
 extension ContainerNode {
@@ -533,39 +769,88 @@ extension WindowNode {
 
 extension ContainerNode {
     fileprivate func onNewNodeAdjustSize(index: Int) {
-        let newSize: Float = 1.0 / Float(children.count)
-        let scale = Float(children.count - 1) / Float(children.count)
-        for (i, child) in children.enumerated() {
-            if i != index {
-                child.base.size *= scale
-            }
+        let others = children.indices.filter { $0 != index }.map { children[$0].base }
+        // The new node shares the space of the siblings that aren't locked.
+        let resizable = resizableNodes(others)
+        let available = resizable.reduce(0.0){$0 + $1.size}
+        let newSize = resizable.isEmpty ? 1.0 : available / Float(resizable.count + 1)
+        let scale = Float(resizable.count) / Float(resizable.count + 1)
+        for child in resizable {
+            child.size *= scale
         }
         children[index].base.size = newSize
+        normalizeSizes()
         check()
     }
     fileprivate func onRemoveNodeAdjustSize() {
         if children.count == 0 {
             return
         }
-        let scale = Float(children.count + 1) / Float(children.count)
-        for child in children {
-            child.base.size *= scale
+        // Give the freed space to the siblings that aren't locked.
+        let resizable = resizableNodes(children.map { $0.base })
+        let total = children.reduce(0.0){$0 + $1.base.size}
+        let resizableTotal = resizable.reduce(0.0){$0 + $1.size}
+        let scale = (1.0 - total + resizableTotal) / resizableTotal
+        for child in resizable {
+            child.size *= scale
+        }
+        normalizeSizes()
+        check()
+    }
+    /// Returns the nodes whose size isn't locked, or all of them if every
+    /// node is locked.
+    private func resizableNodes(_ nodes: [Node]) -> [Node] {
+        let unlocked = nodes.filter { !$0.sizeLocked }
+        return unlocked.isEmpty ? nodes : unlocked
+    }
+    /// Sizes our children in proportion to `weights`, which must have one
+    /// positive entry per child.
+    func setWeights(_ weights: [Float]) {
+        precondition(weights.count == children.count, "need one weight per child")
+        precondition(weights.allSatisfy { $0 > 0 }, "weights must be positive")
+        for (child, weight) in zip(children, weights) {
+            child.base.size = weight
         }
+        normalizeSizes()
         check()
     }
+    /// Rescales child sizes so they sum to exactly 1, so that rounding errors
+    /// from repeated adjustments don't accumulate.
+    fileprivate func normalizeSizes() {
+        guard let last = children.last else {
+            return
+        }
+        let total = children.reduce(0.0){$0 + $1.base.size}
+        var sum: Float = 0.0
+        for child in children.dropLast() {
+            child.base.size /= total
+            sum += child.base.size
+        }
+        last.base.size = 1.0 - sum
+    }
     private func check() {
         // sizes should all sum to 1
         assert(children.reduce(0.0){$0 + $1.base.size}.distance(to: 1.0) < 0.01)
     }
 
-    func refresh_(_ rect: CGRect, _ promises: inout [Promise<()>]?) {
+    func calculateLayout_(_ rect: CGRect, _ frames: inout [(WindowNode, CGRect)]) {
         var start: Float = 0.0
         for child in children {
             let end = start + child.base.size
-            child.refresh(rect: rectForSlice(whole: rect, start, end), &promises)
+            child.calculateLayout(rect: rectForSlice(whole: rect, start, end), &frames)
             start = end
         }
     }
+    fileprivate func rectForChild(_ child: Node, whole: CGRect) -> CGRect {
+        var start: Float = 0.0
+        for sibling in children {
+            if sibling.base === child {
+                break
+            }
+            start += sibling.base.size
+        }
+        return rectForSlice(whole: whole, start, start + child.size)
+    }
     private func rectForSlice(whole: CGRect, _ start: Float, _ end: Float) -> CGRect {
         let start = CGFloat(start)
         let end   = CGFloat(end)
@@ -591,13 +876,8 @@ extension ContainerNode {
 }
 
 extension WindowNode {
-    func refresh_(_ rect: CGRect, _ promises: inout [Promise<()>]?) {
-        // log.debug("RESIZING window to \(rect.rounded()) (\(rect)")
-        let rect = rect.rounded()
-        let promise = window.frame.set(rect)
-        if promises != nil {
-            promises!.append(promise.map({_ in ()}))
-        }
+    func calculateLayout_(_ rect: CGRect, _ frames: inout [(WindowNode, CGRect)]) {
+        frames.append((self, rect.rounded()))
     }
 }
 
@@ -608,6 +888,42 @@ private extension CGRect {
     }
 }
 
+/// Returns the smallest rect containing all of `rects`, or nil if there are none.
+func boundingRect<S: Sequence>(_ rects: S) -> CGRect? where S.Element == CGRect {
+    return rects.reduce(nil as CGRect?) { union, rect in union?.union(rect) ?? rect }
+}
+
+extension CGRect {
+    /// Whether we match `other`, allowing the origin and size to each be off
+    /// by up to the given amount along either axis.
+    ///
+    /// Separate tolerances help with apps that round their size, like
+    /// terminals sizing to a whole number of cells, while positioning freely.
+    func isWithin(_ other: CGRect,
+                  originTolerance: CGFloat = 0,
+                  sizeTolerance: CGFloat = 0) -> Bool {
+        return abs(minX - other.minX) <= originTolerance &&
+            abs(minY - other.minY) <= originTolerance &&
+            abs(width - other.width) <= sizeTolerance &&
+            abs(height - other.height) <= sizeTolerance
+    }
+}
+
+extension NodeKind {
+    /// The current frame of this node on screen.
+    ///
+    /// For containers this is the bounding rect of all the windows inside it,
+    /// or nil if there are none.
+    var frame: CGRect? {
+        switch self {
+        case .window(let node):
+            return node.window.frame.value
+        case .container(let node):
+            return boundingRect(node.children.compactMap { $0.frame })
+        }
+    }
+}
+
 extension NodeKind {
     @discardableResult
     public func resize(byScreenPercentage screenPct: Float, inDirection direction: Direction)
@@ -651,6 +967,7 @@ extension NodeKind {
 
         sibling.base.size -= amountToTake
         resizingNode.base.size += amountToTake
+        parent.normalizeSizes()
         return true
     }
 }
@@ -703,8 +1020,17 @@ extension ContainerNode {
         }
     }
 
-    // FIXME: We need to update the index anytime a child node is added or
-    // removed before the selected node.
+    func onRemoveNodeUpdateSelection(index: Int) {
+        // `children` no longer contains the removed node, so this is the
+        // index of the selected node before removal.
+        let selected = min(selectionData, children.count)
+        if index < selected {
+            selectionData = selected - 1
+        } else {
+            // If the selected node was removed, this selects the node after it.
+            selectionData = selected
+        }
+    }
 }
 
 extension Node {
@@ -720,6 +1046,19 @@ extension Node {
         return parent.selection!.base == self
     }
 
+    /// Whether this node is showing, i.e. not hidden behind another tab of a
+    /// tabbed or stacked container.
+    var isVisible: Bool {
+        var node = self
+        while let parent = node.parent {
+            if !parent.layout.isProportional && !node.isSelected {
+                return false
+            }
+            node = parent
+        }
+        return true
+    }
+
     /// Selects this node locally (within its parent).
     func selectLocally() {
         guard let parent = parent else {
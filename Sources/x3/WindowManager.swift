@@ -1,5 +1,6 @@
 import Carbon
 import os
+import PromiseKit
 import Swindler
 
 public var X3_LOGGER: Logger!
@@ -32,6 +33,23 @@ extension Swindler.State {
             return self.frontmostApplication.value?.mainWindow.value
         }
     }
+
+    /// Returns the screen that contains the largest part of `frame`, or nil if
+    /// it isn't on any screen.
+    func screen(containingMostOf frame: CGRect) -> Screen? {
+        var best: (screen: Screen, area: CGFloat)? = nil
+        for screen in screens {
+            let overlap = screen.frame.intersection(frame)
+            if overlap.isNull {
+                continue
+            }
+            let area = overlap.width * overlap.height
+            if area > 0 && (best == nil || area > best!.area) {
+                best = (screen, area)
+            }
+        }
+        return best?.screen
+    }
 }
 
 extension NodeKind {
@@ -42,11 +60,32 @@ extension NodeKind {
 
 class ContainerNodeWmData: Codable {
     var unstackLayout: Layout?
+
+    /// If set, the container is converted to a stacked layout once it has more
+    /// than this many children.
+    var maxChildren: Int?
 }
 
 let resizeAmt: Float = 0.05
 
 let STATE = CodingUserInfoKey(rawValue: "state")!
+let SCREEN = CodingUserInfoKey(rawValue: "screen")!
+let RESERVED = CodingUserInfoKey(rawValue: "reserved")!
+
+/// A managed window whose frame doesn't match the one the layout gives it.
+struct FrameDiscrepancy {
+    let window: Window
+    let expected: CGRect
+    let actual: CGRect
+
+    /// How far each component of `actual` is from `expected`.
+    var delta: CGRect {
+        return CGRect(x: actual.minX - expected.minX,
+                      y: actual.minY - expected.minY,
+                      width: actual.width - expected.width,
+                      height: actual.height - expected.height)
+    }
+}
 
 /// Defines the basic window management operations and their behavior.
 public final class WindowManager: Encodable, Decodable {
@@ -54,10 +93,66 @@ public final class WindowManager: Encodable, Decodable {
     public var reload: Optional<(WindowManager) -> ()> = nil
 
     var tree: TreeWrapper!
-    var focus: Crawler?
+    var focus: Crawler? {
+        didSet {
+            // A pending splitAndAddNext was meant for the node that was split.
+            if focus?.node != oldValue?.node {
+                addNextTarget = nil
+            }
+        }
+    }
+    private weak var mark: ContainerNode?
 
     var addNewWindows: Bool = false
 
+    /// How long to wait before tiling a newly created window.
+    ///
+    /// Some apps flash a window that is destroyed right away; waiting a bit
+    /// keeps those from causing a reflow.
+    var newWindowDelay: DispatchTimeInterval = .milliseconds(50)
+    private(set) var pendingNewWindows: [Window] = []
+
+    /// Managed windows in the order they were added, oldest first.
+    private var addOrder: [Window] = []
+
+    /// Where `splitAndAddNext` puts the next created window, even if
+    /// `addNewWindows` is off.
+    private weak var addNextTarget: ContainerNode?
+    private var addNextDeadline: DispatchTime = .now()
+
+    /// How long `splitAndAddNext` waits for a window to be created before
+    /// giving up.
+    var addNextWindowTimeout: DispatchTimeInterval = .seconds(10)
+
+    /// Whether `split` always adds a new container, even when the node is
+    /// already the only child of its parent.
+    var splitAlwaysNests: Bool = false
+
+    /// If set, `split` won't nest a node any deeper than this.
+    var maxNestingDepth: Int? = nil
+
+    /// How `moveFocus` picks a window inside the container it moves into.
+    var descentStrategy: Crawler.DescentStrategy = .selected
+
+    /// Whether `moveFocus` carries the focused node along instead of moving
+    /// focus away from it.
+    var focusPullsWindow: Bool = false
+
+    /// How far a window's origin and size may be from what the layout gives it
+    /// before `diagnose` reports it.
+    var frameOriginTolerance: CGFloat = 0
+    var frameSizeTolerance: CGFloat = 0
+
+    /// The layouts visited, in order, by `cycleLayout`.
+    var layoutCycle: [Layout] = [.horizontal, .vertical, .tabbed, .stacked]
+
+    /// Space to leave empty at the edges of the screen, for things like a
+    /// third-party status bar or dock.
+    public var reserved: NSEdgeInsets {
+        get { tree.peek().reserved }
+        set { tree.with { tree in tree.reserved = newValue } }
+    }
+
     public var focusedWindow: Window? {
         guard let node = focus?.node else { return nil }
         guard case .window(let windowNode) = node else { return nil }
@@ -67,6 +162,9 @@ public final class WindowManager: Encodable, Decodable {
     public func encode(to encoder: Encoder) throws {
         var container = encoder.container(keyedBy: CodingKeys.self)
         try container.encode(addNewWindows, forKey: .addNewWindows)
+        try container.encode(focusPullsWindow, forKey: .focusPullsWindow)
+        try container.encode([reserved.top, reserved.left, reserved.bottom, reserved.right],
+                             forKey: .reserved)
         let treeEncoder = JSONEncoder()
         let treeData = try treeEncoder.encode(tree.peek())
         try container.encode(treeData, forKey: .tree)
@@ -80,23 +178,42 @@ public final class WindowManager: Encodable, Decodable {
         state = (decoder.userInfo[STATE]! as! Swindler.State)
         let container = try decoder.container(keyedBy: CodingKeys.self)
         addNewWindows = try container.decode(Bool.self, forKey: .addNewWindows)
+        focusPullsWindow =
+            try container.decodeIfPresent(Bool.self, forKey: .focusPullsWindow) ?? false
         let treeData = try container.decode(Data.self, forKey: .tree)
         log.debug("recovery data: \(String(decoding: treeData, as: UTF8.self))")
         let treeDecoder = JSONDecoder()
         tree = TreeWrapper(try Tree.inflate(
-            from: treeDecoder, data: treeData, screen: state.screens.last!, state: state))
+            from: treeDecoder,
+            data: treeData,
+            screen: (decoder.userInfo[SCREEN] as? Swindler.Screen) ?? state.screens.last!,
+            state: state))
+        if let reserved = decoder.userInfo[RESERVED] as? NSEdgeInsets {
+            tree.peek().reserved = reserved
+        } else if let edges = try container.decodeIfPresent([CGFloat].self, forKey: .reserved),
+                  edges.count == 4 {
+            tree.peek().reserved = NSEdgeInsets(
+                top: edges[0], left: edges[1], bottom: edges[2], right: edges[3])
+        }
         setup()
         // Restore the focus state.
         onFocusedWindowChanged(window: state.focusedWindow)
     }
 
     enum CodingKeys: CodingKey {
-        case addNewWindows, tree
+        case addNewWindows, focusPullsWindow, reserved, tree
     }
 
-    public static func recover(from data: Data, state: Swindler.State) throws -> WindowManager {
+    /// Restores a window manager from `data`. If `reserved` is given, it
+    /// replaces the reserved space that was saved.
+    public static func recover(
+        from data: Data, state: Swindler.State, screen: Swindler.Screen? = nil,
+        reserved: NSEdgeInsets? = nil
+    ) throws -> WindowManager {
         let decoder = JSONDecoder()
         decoder.userInfo[STATE] = state
+        decoder.userInfo[SCREEN] = screen
+        decoder.userInfo[RESERVED] = reserved
         return try decoder.decode(WindowManager.self, from: data)
     }
 
@@ -105,16 +222,26 @@ public final class WindowManager: Encodable, Decodable {
         return try encoder.encode(self)
     }
 
-    public init(state: Swindler.State) {
+    /// Creates a window manager that tiles windows on `screen`, leaving
+    /// `reserved` empty at its edges.
+    ///
+    /// If no screen is given, one is picked from `state`.
+    public init(state: Swindler.State, screen: Swindler.Screen? = nil,
+                reserved: NSEdgeInsets = NSEdgeInsets()) {
         self.state = state
-        self.tree = TreeWrapper(Tree(screen: state.screens.last!))
+        self.tree = TreeWrapper(Tree(screen: screen ?? state.screens.last!))
+        self.tree.peek().reserved = reserved
         setup()
     }
 
     private func setup() {
+        addOrder = tree.peek().root.kind.windows.map { $0.window }
+
         state.on { (event: WindowCreatedEvent) in
-            if self.addNewWindows {
-                self.addWindow(event.window)
+            if let target = self.takeAddNextTarget() {
+                self.addWindowAfterDelay(event.window, into: target)
+            } else if self.addNewWindows {
+                self.addWindowAfterDelay(event.window)
             }
         }
 
@@ -134,99 +261,260 @@ public final class WindowManager: Encodable, Decodable {
     }
 
     public func registerHotKeys(_ hotKeys: HotKeyManager) {
-        hotKeys.register(keyCode: kVK_ANSI_L, modifierKeys: optionKey) {
+        hotKeys.register(keyCode: kVK_ANSI_L, modifierKeys: optionKey,
+                         command: "move focus right") {
             self.moveFocus(.right)
         }
-        hotKeys.register(keyCode: kVK_ANSI_H, modifierKeys: optionKey) {
+        hotKeys.register(keyCode: kVK_ANSI_H, modifierKeys: optionKey,
+                         command: "move focus left") {
             self.moveFocus(.left)
         }
-        hotKeys.register(keyCode: kVK_ANSI_J, modifierKeys: optionKey) {
+        hotKeys.register(keyCode: kVK_ANSI_J, modifierKeys: optionKey,
+                         command: "move focus down") {
             self.moveFocus(.down)
         }
-        hotKeys.register(keyCode: kVK_ANSI_K, modifierKeys: optionKey) {
+        hotKeys.register(keyCode: kVK_ANSI_K, modifierKeys: optionKey,
+                         command: "move focus up") {
             self.moveFocus(.up)
         }
-        hotKeys.register(keyCode: kVK_ANSI_A, modifierKeys: optionKey) {
+        hotKeys.register(keyCode: kVK_ANSI_L, modifierKeys: optionKey | controlKey,
+                         command: "focus window on the right") {
+            self.focusVisual(.right)
+        }
+        hotKeys.register(keyCode: kVK_ANSI_H, modifierKeys: optionKey | controlKey,
+                         command: "focus window on the left") {
+            self.focusVisual(.left)
+        }
+        hotKeys.register(keyCode: kVK_ANSI_J, modifierKeys: optionKey | controlKey,
+                         command: "focus window below") {
+            self.focusVisual(.down)
+        }
+        hotKeys.register(keyCode: kVK_ANSI_K, modifierKeys: optionKey | controlKey,
+                         command: "focus window above") {
+            self.focusVisual(.up)
+        }
+        hotKeys.register(keyCode: kVK_Tab, modifierKeys: optionKey,
+                         command: "focus next tab in group") {
+            self.cycleFocusWithinGroup(.right)
+        }
+        hotKeys.register(keyCode: kVK_Tab, modifierKeys: optionKey | shiftKey,
+                         command: "focus previous tab in group") {
+            self.cycleFocusWithinGroup(.left)
+        }
+        hotKeys.register(keyCode: kVK_ANSI_Grave, modifierKeys: optionKey,
+                         command: "focus previously added window") {
+            self.focusOlderWindow()
+        }
+        hotKeys.register(keyCode: kVK_ANSI_F, modifierKeys: optionKey,
+                         command: "focus biggest window") {
+            self.focusBiggest()
+        }
+        hotKeys.register(keyCode: kVK_ANSI_F, modifierKeys: optionKey | shiftKey,
+                         command: "focus smallest window") {
+            self.focusSmallest()
+        }
+        hotKeys.register(keyCode: kVK_ANSI_A, modifierKeys: optionKey,
+                         command: "focus parent") {
             self.focusParent()
         }
-        hotKeys.register(keyCode: kVK_ANSI_D, modifierKeys: optionKey) {
+        hotKeys.register(keyCode: kVK_ANSI_D, modifierKeys: optionKey,
+                         command: "focus child") {
             self.focusChild()
         }
 
-        hotKeys.register(keyCode: kVK_ANSI_L, modifierKeys: optionKey | shiftKey) {
+        hotKeys.register(keyCode: kVK_ANSI_L, modifierKeys: optionKey | shiftKey,
+                         command: "move node right") {
             self.moveFocusedNode(.right)
         }
-        hotKeys.register(keyCode: kVK_ANSI_H, modifierKeys: optionKey | shiftKey) {
+        hotKeys.register(keyCode: kVK_ANSI_H, modifierKeys: optionKey | shiftKey,
+                         command: "move node left") {
             self.moveFocusedNode(.left)
         }
-        hotKeys.register(keyCode: kVK_ANSI_J, modifierKeys: optionKey | shiftKey) {
+        hotKeys.register(keyCode: kVK_ANSI_J, modifierKeys: optionKey | shiftKey,
+                         command: "move node down") {
             self.moveFocusedNode(.down)
         }
-        hotKeys.register(keyCode: kVK_ANSI_K, modifierKeys: optionKey | shiftKey) {
+        hotKeys.register(keyCode: kVK_ANSI_K, modifierKeys: optionKey | shiftKey,
+                         command: "move node up") {
             self.moveFocusedNode(.up)
         }
 
-        hotKeys.register(keyCode: kVK_RightArrow, modifierKeys: optionKey | cmdKey) {
+        hotKeys.register(keyCode: kVK_ANSI_M, modifierKeys: optionKey,
+                         command: "mark container") {
+            self.markContainer()
+        }
+        hotKeys.register(keyCode: kVK_ANSI_M, modifierKeys: optionKey | shiftKey,
+                         command: "move node to marked container") {
+            self.moveToMark()
+        }
+
+        hotKeys.register(keyCode: kVK_RightArrow, modifierKeys: optionKey | cmdKey,
+                         command: "grow right") {
             self.resize(to: .right, screenPct: resizeAmt)
         }
-        hotKeys.register(keyCode: kVK_LeftArrow, modifierKeys: optionKey | cmdKey) {
+        hotKeys.register(keyCode: kVK_LeftArrow, modifierKeys: optionKey | cmdKey,
+                         command: "grow left") {
             self.resize(to: .left, screenPct: resizeAmt)
         }
-        hotKeys.register(keyCode: kVK_DownArrow, modifierKeys: optionKey | cmdKey) {
+        hotKeys.register(keyCode: kVK_DownArrow, modifierKeys: optionKey | cmdKey,
+                         command: "grow down") {
             self.resize(to: .down, screenPct: resizeAmt)
         }
-        hotKeys.register(keyCode: kVK_UpArrow, modifierKeys: optionKey | cmdKey) {
+        hotKeys.register(keyCode: kVK_UpArrow, modifierKeys: optionKey | cmdKey,
+                         command: "grow up") {
             self.resize(to: .up, screenPct: resizeAmt)
         }
-        hotKeys.register(keyCode: kVK_RightArrow, modifierKeys: optionKey | cmdKey | shiftKey) {
+        hotKeys.register(keyCode: kVK_RightArrow, modifierKeys: optionKey | cmdKey | shiftKey,
+                         command: "shrink right") {
             self.resize(to: .right, screenPct: -resizeAmt)
         }
-        hotKeys.register(keyCode: kVK_LeftArrow, modifierKeys: optionKey | cmdKey | shiftKey) {
+        hotKeys.register(keyCode: kVK_LeftArrow, modifierKeys: optionKey | cmdKey | shiftKey,
+                         command: "shrink left") {
             self.resize(to: .left, screenPct: -resizeAmt)
         }
-        hotKeys.register(keyCode: kVK_DownArrow, modifierKeys: optionKey | cmdKey | shiftKey) {
+        hotKeys.register(keyCode: kVK_DownArrow, modifierKeys: optionKey | cmdKey | shiftKey,
+                         command: "shrink down") {
             self.resize(to: .down, screenPct: -resizeAmt)
         }
-        hotKeys.register(keyCode: kVK_UpArrow, modifierKeys: optionKey | cmdKey | shiftKey) {
+        hotKeys.register(keyCode: kVK_UpArrow, modifierKeys: optionKey | cmdKey | shiftKey,
+                         command: "shrink up") {
             self.resize(to: .up, screenPct: -resizeAmt)
         }
 
-        hotKeys.register(keyCode: kVK_ANSI_D, modifierKeys: optionKey | shiftKey) {
+        hotKeys.register(keyCode: kVK_ANSI_Z, modifierKeys: optionKey,
+                         command: "toggle size lock") {
+            self.toggleSizeLock()
+        }
+
+        hotKeys.register(keyCode: kVK_ANSI_D, modifierKeys: optionKey | shiftKey,
+                         command: "log tree") {
             log.debug("\(String(describing: self.tree.peek().root))")
         }
-        hotKeys.register(keyCode: kVK_ANSI_R, modifierKeys: optionKey | shiftKey) {
+        hotKeys.register(keyCode: kVK_ANSI_I, modifierKeys: optionKey | shiftKey,
+                         command: "diagnose window frames") {
+            self.diagnose().done { discrepancies in
+                if discrepancies.isEmpty {
+                    log.info("All windows match the layout")
+                }
+                for d in discrepancies {
+                    log.info("""
+                        \(String(describing: d.window)): expected \(String(describing: d.expected)), \
+                        got \(String(describing: d.actual)) (delta \(String(describing: d.delta)))
+                    """)
+                }
+            }.catch { err in
+                log.error("Error diagnosing window frames: \(String(describing: err))")
+            }
+        }
+        hotKeys.register(keyCode: kVK_ANSI_R, modifierKeys: optionKey | shiftKey,
+                         command: "reload") {
             self.reload?(self)
         }
+        hotKeys.register(keyCode: kVK_ANSI_B, modifierKeys: optionKey | shiftKey,
+                         command: "log hotkeys") {
+            if let data = try? hotKeys.exportBindings() {
+                log.debug("\(String(decoding: data, as: UTF8.self))")
+            }
+        }
 
-        hotKeys.register(keyCode: kVK_ANSI_X, modifierKeys: optionKey) {
+        hotKeys.register(keyCode: kVK_ANSI_X, modifierKeys: optionKey,
+                         command: "add focused window") {
             if let window = self.state.focusedWindow {
                 self.addWindow(window)
             }
         }
-        hotKeys.register(keyCode: kVK_ANSI_R, modifierKeys: optionKey) {
+        hotKeys.register(keyCode: kVK_ANSI_R, modifierKeys: optionKey,
+                         command: "refresh") {
             self.tree.peek().refresh()
         }
 
-        hotKeys.register(keyCode: kVK_ANSI_Minus, modifierKeys: optionKey) {
+        hotKeys.register(keyCode: kVK_ANSI_Minus, modifierKeys: optionKey,
+                         command: "split vertically") {
             self.split(.vertical)
         }
-        hotKeys.register(keyCode: kVK_ANSI_Backslash, modifierKeys: optionKey) {
+        hotKeys.register(keyCode: kVK_ANSI_Backslash, modifierKeys: optionKey,
+                         command: "split horizontally") {
             self.split(.horizontal)
         }
+        hotKeys.register(keyCode: kVK_ANSI_Minus, modifierKeys: optionKey | shiftKey,
+                         command: "split vertically and add next window") {
+            self.splitAndAddNext(.vertical)
+        }
+        hotKeys.register(keyCode: kVK_ANSI_Backslash, modifierKeys: optionKey | shiftKey,
+                         command: "split horizontally and add next window") {
+            self.splitAndAddNext(.horizontal)
+        }
 
-        hotKeys.register(keyCode: kVK_ANSI_T, modifierKeys: optionKey) {
+        hotKeys.register(keyCode: kVK_ANSI_T, modifierKeys: optionKey,
+                         command: "tabbed layout") {
             self.stack(layout: .tabbed)
         }
-        hotKeys.register(keyCode: kVK_ANSI_S, modifierKeys: optionKey) {
+        hotKeys.register(keyCode: kVK_ANSI_S, modifierKeys: optionKey,
+                         command: "stacked layout") {
             self.stack(layout: .stacked)
         }
-        hotKeys.register(keyCode: kVK_ANSI_E, modifierKeys: optionKey) {
+        hotKeys.register(keyCode: kVK_ANSI_E, modifierKeys: optionKey,
+                         command: "unstack layout") {
             self.unstack()
         }
+        hotKeys.register(keyCode: kVK_Space, modifierKeys: optionKey,
+                         command: "cycle layout") {
+            self.cycleLayout()
+        }
+        hotKeys.register(keyCode: kVK_Space, modifierKeys: optionKey | shiftKey,
+                         command: "flip all containers") {
+            self.flipAll()
+        }
 
-        hotKeys.register(keyCode: kVK_Return, modifierKeys: optionKey) {
+        hotKeys.register(keyCode: kVK_Delete, modifierKeys: optionKey | shiftKey,
+                         command: "reset layout") {
+            self.resetLayout()
+        }
+
+        hotKeys.register(keyCode: kVK_Return, modifierKeys: optionKey,
+                         command: "toggle adding new windows") {
             self.addNewWindows = !self.addNewWindows
         }
+        hotKeys.register(keyCode: kVK_ANSI_P, modifierKeys: optionKey,
+                         command: "toggle focus pulling window") {
+            self.focusPullsWindow = !self.focusPullsWindow
+        }
+    }
+
+    /// Puts every window back where the layout says it should be.
+    ///
+    /// Call this when the system wakes from sleep, since windows may have been
+    /// moved around in the meantime.
+    public func onSystemWoke() {
+        log.debug("System woke; refreshing layout")
+        tree.peek().refresh()
+    }
+
+    /// Returns every managed window that isn't where the layout puts it.
+    ///
+    /// Frames are queried from each window rather than read from Swindler's
+    /// cache, so this also catches changes we weren't notified about. An empty
+    /// result means all windows are under our control.
+    func diagnose() -> Promise<[FrameDiscrepancy]> {
+        let layout = tree.peek().calculateLayout()
+        return when(fulfilled: layout.map { (node, _) in
+            node.window.frame.refresh()
+        }).map { actualFrames in
+            zip(layout, actualFrames).compactMap { (entry, actual) in
+                let (node, expected) = entry
+                if actual.isWithin(expected,
+                                   originTolerance: self.frameOriginTolerance,
+                                   sizeTolerance: self.frameSizeTolerance) {
+                    return nil
+                }
+                return FrameDiscrepancy(window: node.window, expected: expected, actual: actual)
+            }
+        }
+    }
+
+    /// Returns the frames the current layout assigns to each managed window.
+    func layoutSnapshot() -> LayoutSnapshot {
+        return tree.peek().snapshot()
     }
 
     func addWindow(_ window: Window) {
@@ -234,19 +522,27 @@ public final class WindowManager: Encodable, Decodable {
     }
 
     // For testing only.
-    func addWindowReturningNode(_ window: Window) -> WindowNode? {
+    /// Adds `window` next to the focused node, or to the end of `target` if
+    /// the focused node isn't in it.
+    func addWindowReturningNode(_ window: Window,
+                                into target: ContainerNode? = nil) -> WindowNode? {
         if tree.peek().root.contains(window: window) {
             return nil
         }
 
         var node: WindowNode!
         tree.with { tree in
-            if let focusNode = focus?.node,
+            if let target = target, target.kind.findRoot() == tree.root,
+               focus?.node.parent != target {
+                node = target.createWindow(window, at: .end)
+            } else if let focusNode = focus?.node,
                let parent = focusNode.base.parent {
                 node = parent.createWindow(window, at: .after(focusNode))
             } else {
                 node = tree.root.createWindow(window, at: .end)
             }
+            stackIfCrowded(node.parent)
+            addOrder.append(window)
 
             // Question: Do we always want to focus new windows?
             node.selectGlobally()
@@ -257,7 +553,27 @@ public final class WindowManager: Encodable, Decodable {
         return node
     }
 
-    private func onWindowDestroyed(_ window: Window) {
+    private func addWindowAfterDelay(_ window: Window, into target: ContainerNode? = nil) {
+        pendingNewWindows.append(window)
+        DispatchQueue.main.asyncAfter(deadline: .now() + newWindowDelay) { [weak target] in
+            // If the window was destroyed in the meantime, it's no longer pending.
+            guard let index = self.pendingNewWindows.firstIndex(of: window) else { return }
+            self.pendingNewWindows.remove(at: index)
+            _ = self.addWindowReturningNode(window, into: target)
+        }
+    }
+
+    /// Returns the container set by `splitAndAddNext`, unless it has expired,
+    /// and clears it.
+    private func takeAddNextTarget() -> ContainerNode? {
+        defer { addNextTarget = nil }
+        guard DispatchTime.now() < addNextDeadline else { return nil }
+        return addNextTarget
+    }
+
+    func onWindowDestroyed(_ window: Window) {
+        pendingNewWindows.removeAll { $0 == window }
+        addOrder.removeAll { $0 == window }
         tree.with { tree in
             if let node = tree.find(window: window) {
                 let parent = node.parent
@@ -273,7 +589,16 @@ public final class WindowManager: Encodable, Decodable {
     }
 
     func moveFocus(_ direction: Direction) {
-        guard let next = focus?.move(direction, leaf: .selected) else {
+        if focusPullsWindow {
+            // The root can't be moved.
+            guard canMoveFocusedNode(direction) else {
+                return
+            }
+            moveFocusedNode(direction)
+            raiseFocus()
+            return
+        }
+        guard let next = focus?.move(direction, leaf: descentStrategy) else {
             return
         }
         focus = next
@@ -282,6 +607,104 @@ public final class WindowManager: Encodable, Decodable {
         raiseFocus()
     }
 
+    /// Whether `moveFocus` would change the focus.
+    func canMoveFocus(_ direction: Direction) -> Bool {
+        if focusPullsWindow {
+            return canMoveFocusedNode(direction)
+        }
+        return focus?.move(direction, leaf: descentStrategy) != nil
+    }
+
+    /// Moves focus to the next tab of the nearest tabbed or stacked container
+    /// around the focused node, wrapping around at the ends.
+    ///
+    /// Unlike `moveFocus`, this never leaves the group.
+    func cycleFocusWithinGroup(_ direction: Direction) {
+        guard var child = focus?.node else { return }
+        var group = child.parent
+        while let container = group, container.layout.isProportional {
+            child = container.kind
+            group = container.parent
+        }
+        guard let container = group else { return }
+        let count = container.children.count
+        let index = container.children.firstIndex(of: child)!
+        var next = container.children[(index + direction.value + count) % count]
+        while let selection = next.selection {
+            next = selection
+        }
+        focus = Crawler(at: next)
+        next.base.selectGlobally()
+        raiseFocus()
+    }
+
+    /// Focuses the window that was added before the focused one, across all
+    /// apps. Starting from the newest window, repeated calls walk back to the
+    /// oldest and then wrap around.
+    func focusOlderWindow() {
+        let tree = self.tree.peek()
+        let windows = addOrder.filter { tree.find(window: $0) != nil }
+        guard !windows.isEmpty else { return }
+        var index = windows.count
+        if let focused = focusedWindow, let focusedIndex = windows.firstIndex(of: focused) {
+            index = focusedIndex
+        }
+        let node = tree.find(window: windows[(index - 1 + windows.count) % windows.count])!
+        focus = Crawler(at: node)
+        node.selectGlobally()
+        raiseFocus()
+    }
+
+    /// Moves focus to the nearest window on screen in the given direction.
+    ///
+    /// Unlike `moveFocus`, which follows the tree, this goes by where windows
+    /// are actually laid out. The two can differ when containers are nested
+    /// unevenly.
+    func focusVisual(_ direction: Direction) {
+        guard let focusNode = focus?.node else { return }
+        let frames = tree.peek().calculateLayout()
+        let focused = frames.filter { focusNode.base.contains(window: $0.0.window) }
+        guard let current = boundingRect(focused.map { $0.1 }) else { return }
+        let candidates = frames.filter { (node, frame) in
+            node.isVisible &&
+                !focusNode.base.contains(window: node.window) &&
+                frame.lies(direction, of: current)
+        }
+        guard let (next, _) = candidates.min(by: { (lhs, rhs) in
+            lhs.1.nearness(to: current, along: direction.orientation) <
+                rhs.1.nearness(to: current, along: direction.orientation)
+        }) else {
+            return
+        }
+        focus = Crawler(at: next.kind)
+        next.selectGlobally()
+        raiseFocus()
+    }
+
+    /// Focuses the window with the largest area on screen.
+    func focusBiggest() {
+        focusWindow(withArea: >)
+    }
+
+    /// Focuses the window with the smallest area on screen.
+    func focusSmallest() {
+        focusWindow(withArea: <)
+    }
+
+    private func focusWindow(withArea isPreferred: (CGFloat, CGFloat) -> Bool) {
+        var best: (node: WindowNode, area: CGFloat)? = nil
+        for (node, frame) in tree.peek().calculateLayout() where node.isVisible {
+            let area = frame.width * frame.height
+            if best == nil || isPreferred(area, best!.area) {
+                best = (node, area)
+            }
+        }
+        guard let node = best?.node else { return }
+        focus = Crawler(at: node)
+        node.selectGlobally()
+        raiseFocus()
+    }
+
     func focusParent() {
         guard let parent = focus?.node.base.parent else {
             return
@@ -302,7 +725,51 @@ public final class WindowManager: Encodable, Decodable {
         }
         tree.with { tree in
             node.move(inDirection: direction)
+            stackIfCrowded(node.parent)
+        }
+    }
+
+    /// Whether `moveFocusedNode` would change the tree.
+    func canMoveFocusedNode(_ direction: Direction) -> Bool {
+        return focus?.node.canMove(inDirection: direction) ?? false
+    }
+
+    /// Marks a container as the destination for `moveToMark`.
+    ///
+    /// If a window is focused, its parent is marked.
+    func markContainer() {
+        guard let node = focus?.node else { return }
+        mark = node.containerNode ?? node.parent
+    }
+
+    /// Moves the focused node into the container marked with `markContainer`.
+    func moveToMark() {
+        guard let node = focus?.node, let mark = mark else { return }
+        // The marked container may have been removed from the tree since.
+        guard mark.kind.findRoot() == tree.peek().root else {
+            self.mark = nil
+            return
+        }
+        guard node.parent != nil && node.parent != mark else { return }
+        // Don't move a node inside itself.
+        var ancestor: ContainerNode? = mark
+        while let cur = ancestor {
+            if cur == node.base {
+                return
+            }
+            ancestor = cur.parent
         }
+        tree.with { tree in
+            node.node.reparent(mark, at: .end)
+            stackIfCrowded(mark)
+        }
+    }
+
+    /// Locks or unlocks the size of the focused node, so that it keeps its size
+    /// when windows are added next to it or removed.
+    func toggleSizeLock() {
+        guard let node = focus?.node else { return }
+        node.base.sizeLocked = !node.base.sizeLocked
     }
 
     func resize(to direction: Direction, screenPct: Float) {
@@ -376,17 +843,33 @@ public final class WindowManager: Encodable, Decodable {
         }
     }
 
+    /// Splits the current node, then places the next window that is created
+    /// next to it in the new container.
+    ///
+    /// This is cancelled if focus moves or no window is created within
+    /// `addNextWindowTimeout`.
+    func splitAndAddNext(_ layout: Layout) {
+        split(layout)
+        addNextTarget = focus?.node.parent ?? tree.peek().root
+        addNextDeadline = .now() + addNextWindowTimeout
+    }
+
     func putContainerAbove(_ node: NodeKind, layout: Layout) {
         // FIXME: This modifies the tree without calling tree.with!
         // In this case, it does not affect sizing, but we need a more principled
         // approach here. Think Binder in rustc.
-        if let parent = node.base.parent, parent.children.count == 1 {
+        if !splitAlwaysNests, let parent = node.base.parent, parent.children.count == 1 {
             // This node already has a container around itself; just set the layout.
             // This won't affect sizes.
             parent.layout = layout
             return
         }
 
+        // The new container pushes everything under node down a level.
+        if let maxDepth = maxNestingDepth, node.base.depth + node.base.height + 1 > maxDepth {
+            return
+        }
+
         node.node.insertParent(layout: layout)
     }
 
@@ -402,10 +885,62 @@ public final class WindowManager: Encodable, Decodable {
         }
     }
 
+    /// Discards all containers, leaving every window side by side in a
+    /// horizontal root with equal sizes.
+    ///
+    /// The root's `maxChildren` still applies, so a root with too many windows
+    /// ends up stacked.
+    func resetLayout() {
+        tree.with { tree in
+            tree.flatten()
+            if let window = focus?.node.windowNode {
+                window.selectGlobally()
+            } else if focus?.node != tree.root.kind {
+                focus = tree.root.selection?.toCrawler()
+            }
+            stackIfCrowded(tree.root)
+        }
+    }
+
+    /// Flips the orientation of every container in the tree, keeping sizes and
+    /// selection.
+    func flipAll() {
+        tree.with { tree in
+            flip(tree.root)
+        }
+    }
+
+    private func flip(_ container: ContainerNode) {
+        container.layout = container.layout.flipped
+        container.wmData.unstackLayout = container.wmData.unstackLayout?.flipped
+        for case .container(let child) in container.children {
+            flip(child)
+        }
+    }
+
+    /// Converts `container` to a stacked layout if it has more children than
+    /// its `maxChildren` allows.
+    private func stackIfCrowded(_ container: ContainerNode?) {
+        guard let container = container,
+              let maxChildren = container.wmData.maxChildren,
+              container.children.count > maxChildren,
+              container.layout.isProportional else { return }
+        container.wmData.unstackLayout = container.layout
+        container.layout = .stacked
+    }
+
     /// Converts the parent of the current node back to the unstacked layout it
     /// was in previously.
+    ///
+    /// If the current node is itself a stacked or tabbed container, that
+    /// container is unstacked instead.
     func unstack() {
-        guard let parent = self.focus?.node.parent else { return }
+        guard let node = self.focus?.node else { return }
+        var container = node.parent
+        if let focused = node.containerNode, !focused.layout.isProportional {
+            container = focused
+        }
+        guard let parent = container else { return }
         if parent.layout == .horizontal || parent.layout == .vertical {
             return
         }
@@ -414,4 +949,56 @@ public final class WindowManager: Encodable, Decodable {
             parent.layout = parent.wmData.unstackLayout!
         }
     }
+
+    /// Converts the parent of the current node to the layout following its
+    /// current one in `layoutCycle`, wrapping around at the end.
+    func cycleLayout() {
+        guard !layoutCycle.isEmpty else { return }
+        guard let parent = self.focus?.node.parent else { return }
+        var next = layoutCycle[0]
+        if let index = layoutCycle.firstIndex(of: parent.layout) {
+            next = layoutCycle[(index + 1) % layoutCycle.count]
+        }
+        tree.with { tree in
+            // Remember where we came from so unstack keeps working.
+            if parent.layout.isProportional {
+                parent.wmData.unstackLayout = parent.layout
+            }
+            parent.layout = next
+        }
+    }
+}
+
+private extension CGRect {
+    /// Whether our center lies past the edge of `other` in `direction`.
+    ///
+    /// Note that y coordinates go up, so `.up` is toward larger y.
+    func lies(_ direction: Direction, of other: CGRect) -> Bool {
+        switch direction {
+        case .left:  return midX <= other.minX
+        case .right: return midX >= other.maxX
+        case .down:  return midY <= other.minY
+        case .up:    return midY >= other.maxY
+        }
+    }
+
+    /// How near we are to `other` when moving along `orientation`; smaller is
+    /// nearer.
+    ///
+    /// Rects that line up with `other` across the direction of movement come
+    /// first, then we compare the distance between centers along the
+    /// direction, then across it.
+    func nearness(to other: CGRect, along orientation: Orientation)
+    -> (CGFloat, CGFloat, CGFloat) {
+        let dx = abs(midX - other.midX)
+        let dy = abs(midY - other.midY)
+        switch orientation {
+        case .horizontal:
+            let overlaps = minY < other.maxY && maxY > other.minY
+            return (overlaps ? 0 : 1, dx, dy)
+        case .vertical:
+            let overlaps = minX < other.maxX && maxX > other.minX
+            return (overlaps ? 0 : 1, dy, dx)
+        }
+    }
 }
@@ -121,6 +121,12 @@ public class AppDelegate: NSObject, NSApplicationDelegate {
 
         hotkeys = HotKeyManager()
 
+        NSWorkspace.shared.notificationCenter.addObserver(
+            forName: NSWorkspace.didWakeNotification, object: nil, queue: .main
+        ) { _ in
+            self.manager?.onSystemWoke()
+        }
+
         Swindler.initialize().done { state in
             log.debug("done with init. args: \(CommandLine.arguments)")
             if CommandLine.arguments.contains(RECOVER) {
@@ -32,10 +32,14 @@ class CrawlerSpec: QuickSpec {
 
                 describe("Crawler") {
                     func checkMove(_ direction: Direction, leaf: Crawler.DescentStrategy,
-                                from: FakeWindow, to: FakeWindow,
+                                from: FakeWindow, to: FakeWindow?,
                                 file: FileString = #file, line: UInt = #line) {
                         let crawler = Crawler(at: root.find(window: from.window)!)
                         let result = crawler.move(direction, leaf: leaf)?.node
+                        guard let to = to else {
+                            expect(result, file: file, line: line).to(beNil())
+                            return
+                        }
                         expect(result, file: file, line: line).to(equal(
                             root.find(window: to.window)!.kind
                         ))
@@ -57,11 +61,17 @@ class CrawlerSpec: QuickSpec {
                     }
 
                     it("moves predictably") {
-                        checkMove(.right, leaf: .selected, from: d, to: e)
-                        checkMove(.up,    leaf: .selected, from: d, to: c)
                         checkMove(.left,  leaf: .selected, from: d, to: a)
                     }
 
+                    it("moves between the children of proportional containers only") {
+                        // Tabbed and stacked containers are moved past as a unit.
+                        checkMove(.right, leaf: .selected, from: d,
+                                  to: horizontal.isProportional ? e : nil)
+                        checkMove(.up,    leaf: .selected, from: d,
+                                  to: vertical.isProportional ? c : nil)
+                    }
+
                     it("follows selection path when DescentStrategy.selected is used") {
                         root.find(window: e.window)!.selectGlobally()
                         checkMove(.right, leaf: .selected, from: a, to: e)
@@ -69,6 +79,21 @@ class CrawlerSpec: QuickSpec {
                         checkMove(.right, leaf: .selected, from: a, to: c)
                     }
 
+                    it("descends to first children when DescentStrategy.first is used") {
+                        root.find(window: e.window)!.selectGlobally()
+                        checkMove(.right, leaf: .first, from: a, to: b)
+                        checkMove(.down,  leaf: .first, from: c,
+                                  to: vertical.isProportional ? d : nil)
+                    }
+
+                    it("descends toward where it came from when DescentStrategy.nearest is used") {
+                        root.find(window: e.window)!.selectGlobally()
+                        checkMove(.right, leaf: .nearest, from: a,
+                                  to: horizontal.isProportional ? d : e)
+                        checkMove(.down,  leaf: .nearest, from: c,
+                                  to: vertical.isProportional ? e : nil)
+                    }
+
                     it("ascends") {
                         var crawl = Crawler(at: root.find(window: d.window)!)
                         crawl = crawl.ascend()!
@@ -108,6 +133,26 @@ class CrawlerSpec: QuickSpec {
                         expect(root.children) == [aNode, bNode, cNode]
                     }
 
+                    it("reports whether a move would change the tree") {
+                        var aNode, bNode: NodeKind!
+                        let oldRoot = root
+                        root.makeWindow(a.window) { aNode = $0.kind }
+                            .makeWindow(b.window) { bNode = $0.kind }
+
+                        expect(root.kind.canMove(inDirection: .left)) == false
+                        expect(aNode.canMove(inDirection: .left)) == false
+                        expect(bNode.canMove(inDirection: .right)) == false
+                        expect(aNode.canMove(inDirection: .right)) == true
+                        expect(bNode.canMove(inDirection: .down)) == true
+
+                        bNode.move(inDirection: .down)
+                        expect(tree.root.children) == [oldRoot.kind, bNode]
+                        expect(aNode.canMove(inDirection: .down)) == true
+                        expect(bNode.canMove(inDirection: .down)) == false
+                        // This would create a new horizontal root.
+                        expect(bNode.canMove(inDirection: .left)) == true
+                    }
+
                     var aNode, bNode, cNode, dNode, eNode: NodeKind!
                     var leftChild, rightChild, grandChild: ContainerNode!
                     func makeNestedLayout(rightChild rcl: Layout, grandChild gcl: Layout) {
@@ -0,0 +1,34 @@
+import Cocoa
+import Carbon
+import Nimble
+import Quick
+@testable import x3
+
+class HotKeyManagerSpec: QuickSpec {
+    override func spec() {
+        describe("bindings") {
+            var hotKeys: HotKeyManager!
+
+            beforeEach {
+                hotKeys = HotKeyManager()
+                hotKeys.register(keyCode: kVK_F19, modifierKeys: optionKey | shiftKey,
+                                 command: "first") {}
+                hotKeys.register(keyCode: kVK_F18, modifierKeys: cmdKey | controlKey,
+                                 command: "second") {}
+            }
+
+            it("records what was registered") {
+                expect(hotKeys.bindings) == [
+                    HotKeyBinding(keyCode: kVK_F19, modifiers: ["option", "shift"], command: "first"),
+                    HotKeyBinding(keyCode: kVK_F18, modifiers: ["control", "cmd"], command: "second"),
+                ]
+            }
+
+            it("exports as JSON") {
+                let data = try! hotKeys.exportBindings()
+                let decoded = try! JSONDecoder().decode([HotKeyBinding].self, from: data)
+                expect(decoded) == hotKeys.bindings
+            }
+        }
+    }
+}
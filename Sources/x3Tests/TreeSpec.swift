@@ -10,6 +10,23 @@ private func r(x: Int, y: Int, w: Int, h: Int) -> CGRect {
     return CGRect(x: x, y: y, width: w, height: h)
 }
 
+/// A SplitMix64 generator, so randomized specs are reproducible.
+private struct SeededGenerator: RandomNumberGenerator {
+    private var state: UInt64
+
+    init(seed: UInt64) {
+        state = seed
+    }
+
+    mutating func next() -> UInt64 {
+        state &+= 0x9e3779b97f4a7c15
+        var z = state
+        z = (z ^ (z >> 30)) &* 0xbf58476d1ce4e5b9
+        z = (z ^ (z >> 27)) &* 0x94d049bb133111eb
+        return z ^ (z >> 31)
+    }
+}
+
 class TreeSpec: QuickSpec {
     override func spec() {
         var fakeApp: FakeApplication!
@@ -30,6 +47,42 @@ class TreeSpec: QuickSpec {
             e = createWindowForApp(fakeApp, "E")
         }
 
+        describe("boundingRect") {
+            it("is nil for no rects") {
+                expect(boundingRect([CGRect]())).to(beNil())
+            }
+
+            it("is the rect itself for a single rect") {
+                expect(boundingRect([r(x: 10, y: 20, w: 30, h: 40)])) == r(x: 10, y: 20, w: 30, h: 40)
+            }
+
+            it("covers disjoint rects") {
+                let rects = [r(x: 0, y: 0, w: 10, h: 10), r(x: 100, y: 50, w: 20, h: 30)]
+                expect(boundingRect(rects)) == r(x: 0, y: 0, w: 120, h: 80)
+            }
+        }
+
+        describe("CGRect.isWithin") {
+            let frame = r(x: 100, y: 50, w: 800, h: 600)
+
+            it("requires an exact match by default") {
+                expect(frame.isWithin(frame)) == true
+                expect(frame.isWithin(r(x: 100, y: 50, w: 801, h: 600))) == false
+            }
+
+            it("allows a size rounded to a cell increment") {
+                let terminal = r(x: 100, y: 50, w: 793, h: 595)
+                expect(terminal.isWithin(frame, sizeTolerance: 8)) == true
+                expect(terminal.isWithin(frame, originTolerance: 8)) == false
+            }
+
+            it("checks origin and size tolerances separately") {
+                let moved = r(x: 104, y: 50, w: 800, h: 600)
+                expect(moved.isWithin(frame, sizeTolerance: 8)) == false
+                expect(moved.isWithin(frame, originTolerance: 8)) == true
+            }
+        }
+
         context("with a single screen") {
             var screen: FakeScreen!
             var tree: Tree!
@@ -114,6 +167,32 @@ class TreeSpec: QuickSpec {
                 }
             }
 
+            it("does not raise windows when setting their frames") {
+                fakeApp.mainWindow = c
+                expect(fakeApp.mainWindow).toEventually(equal(c))
+                return firstly { () -> Promise<()> in
+                    tree.root.createWindow(a.window, at: .end)
+                    tree.root.createWindow(b.window, at: .end)
+                    return tree.awaitRefresh()
+                }.done {
+                    expect(a.frame).to(equal(r(x: 0,    y: 50, w: 1000, h: 1000)))
+                    expect(b.frame).to(equal(r(x: 1000, y: 50, w: 1000, h: 1000)))
+                    expect(fakeApp.mainWindow).to(equal(c))
+                }
+            }
+
+            it("leaves reserved space at the edges of the screen empty") {
+                return firstly { () -> Promise<()> in
+                    tree.reserved = NSEdgeInsets(top: 20, left: 100, bottom: 0, right: 0)
+                    tree.root.createWindow(a.window, at: .end)
+                    tree.root.createWindow(b.window, at: .end)
+                    return tree.awaitRefresh()
+                }.done {
+                    expect(a.frame).to(equal(r(x: 100,  y: 50, w: 950, h: 980)))
+                    expect(b.frame).to(equal(r(x: 1050, y: 50, w: 950, h: 980)))
+                }
+            }
+
             it("removes windows when they are destroyed") { () -> Promise<()> in
                 let anode = tree.root.createWindow(a.window, at: .end)
                 let bnode = tree.root.createWindow(b.window, at: .end)
@@ -138,6 +217,84 @@ class TreeSpec: QuickSpec {
                 }
             }
 
+            it("preserves sizes when moving a child to another index") {
+                let aNode = tree.root.createWindow(a.window, at: .end)
+                let bNode = tree.root.createWindow(b.window, at: .end)
+                let cNode = tree.root.createWindow(c.window, at: .end)
+                return firstly { () -> Promise<()> in
+                    aNode.kind.resize(byScreenPercentage: 0.1, inDirection: .right)
+                    tree.root.moveChild(cNode.kind, to: 0)
+                    return tree.awaitRefresh()
+                }.done {
+                    expect(tree.root.children) == [cNode.kind, aNode.kind, bNode.kind]
+                    expect(c.frame).to(equal(r(x: 0,    y: 50, w: 667, h: 1000)))
+                    expect(a.frame).to(equal(r(x: 667,  y: 50, w: 867, h: 1000)))
+                    expect(b.frame).to(equal(r(x: 1533, y: 50, w: 467, h: 1000)))
+                }
+            }
+
+            it("keeps the size of locked nodes when siblings are added and removed") {
+                let aNode = tree.root.createWindow(a.window, at: .end)
+                tree.root.createWindow(b.window, at: .end)
+                var cNode: WindowNode!
+                return firstly { () -> Promise<()> in
+                    aNode.sizeLocked = true
+                    cNode = tree.root.createWindow(c.window, at: .end)
+                    return tree.awaitRefresh()
+                }.done {
+                    expect(a.frame).to(equal(r(x: 0,    y: 50, w: 1000, h: 1000)))
+                    expect(b.frame).to(equal(r(x: 1000, y: 50, w: 500,  h: 1000)))
+                    expect(c.frame).to(equal(r(x: 1500, y: 50, w: 500,  h: 1000)))
+                }.then { () -> Promise<()> in
+                    cNode.destroy()
+                    return tree.awaitRefresh()
+                }.done {
+                    expect(a.frame).to(equal(r(x: 0,    y: 50, w: 1000, h: 1000)))
+                    expect(b.frame).to(equal(r(x: 1000, y: 50, w: 1000, h: 1000)))
+                }
+            }
+
+            it("sizes children by weight") {
+                return firstly { () -> Promise<()> in
+                    tree.root.createWindow(a.window, at: .end)
+                    tree.root.createContainer(layout: .vertical, at: .end,
+                                              weights: [3, 1]) { n in
+                        n.createWindow(b.window, at: .end)
+                        n.createWindow(c.window, at: .end)
+                    }
+                    tree.root.setWeights([3, 1])
+                    return tree.awaitRefresh()
+                }.done {
+                    expect(a.frame).to(equal(r(x: 0,    y: 50,  w: 1500, h: 1000)))
+                    expect(b.frame).to(equal(r(x: 1500, y: 300, w: 500,  h: 750)))
+                    expect(c.frame).to(equal(r(x: 1500, y: 50,  w: 500,  h: 250)))
+                }
+            }
+
+            it("swaps windows within a container") {
+                let aNode = tree.root.createWindow(a.window, at: .end)
+                let bNode = tree.root.createWindow(b.window, at: .end)
+                let cNode = tree.root.createWindow(c.window, at: .end)
+                return firstly { () -> Promise<()> in
+                    aNode.kind.resize(byScreenPercentage: 0.1, inDirection: .right)
+                    cNode.selectGlobally()
+                    expect(tree.swap(a.window, c.window)) == true
+                    return tree.awaitRefresh()
+                }.done {
+                    expect(tree.root.children) == [cNode.kind, bNode.kind, aNode.kind]
+                    expect(tree.root.selection) == cNode.kind
+                    expect(c.frame).to(equal(r(x: 0,    y: 50, w: 867, h: 1000)))
+                    expect(b.frame).to(equal(r(x: 867,  y: 50, w: 467, h: 1000)))
+                    expect(a.frame).to(equal(r(x: 1333, y: 50, w: 667, h: 1000)))
+                }
+            }
+
+            it("does not swap windows that aren't in the tree") {
+                tree.root.createWindow(a.window, at: .end)
+                expect(tree.swap(a.window, b.window)) == false
+                expect(tree.root.children.map { $0.windowNode?.window }) == [a.window]
+            }
+
             it("allows nesting a horizontal container inside horizontal") {
                 return firstly { () -> Promise<()> in
                     tree.root.createWindow(a.window, at: .end)
@@ -219,6 +376,20 @@ class TreeSpec: QuickSpec {
                 }
             }
 
+            it("gives every window the full screen when the root is tabbed") {
+                return firstly { () -> Promise<()> in
+                    tree.root.layout = .tabbed
+                    tree.root.createWindow(a.window, at: .end)
+                    tree.root.createWindow(b.window, at: .end)
+                    tree.root.createWindow(c.window, at: .end)
+                    return tree.awaitRefresh()
+                }.done {
+                    expect(a.frame).to(equal(r(x: 0, y: 50, w: 2000, h: 1000)))
+                    expect(b.frame).to(equal(r(x: 0, y: 50, w: 2000, h: 1000)))
+                    expect(c.frame).to(equal(r(x: 0, y: 50, w: 2000, h: 1000)))
+                }
+            }
+
             context("when containers are nested 3 deep") {
                 var child, grandchild: ContainerNode!
 
@@ -241,6 +412,37 @@ class TreeSpec: QuickSpec {
                     expect(d.frame).to(equal(r(x: 1500, y: 50,  w: 500,  h: 500)))
                 }
 
+                it("swaps windows across containers") {
+                    return firstly { () -> Promise<()> in
+                        tree.find(window: d.window)!.selectGlobally()
+                        expect(tree.swap(a.window, d.window)) == true
+                        return tree.awaitRefresh()
+                    }.done {
+                        expect(d.frame).to(equal(r(x: 0,    y: 50,  w: 1000, h: 1000)))
+                        expect(b.frame).to(equal(r(x: 1000, y: 550, w: 1000, h: 500)))
+                        expect(c.frame).to(equal(r(x: 1000, y: 50,  w: 500,  h: 500)))
+                        expect(a.frame).to(equal(r(x: 1500, y: 50,  w: 500,  h: 500)))
+                        expect(tree.find(window: a.window)?.parent) == grandchild
+                        expect(tree.find(window: d.window)?.parent) == tree.root
+                        expect(grandchild.selection) == tree.find(window: a.window)?.kind
+                    }
+                }
+
+                it("computes single node frames that match the full layout") {
+                    let layout = tree.calculateLayout()
+                    expect(layout.count) == 4
+                    for (node, frame) in layout {
+                        expect(tree.frame(of: node)) == frame
+                    }
+                    expect(tree.frame(of: grandchild)) == r(x: 1000, y: 50, w: 1000, h: 500)
+                    expect(tree.frame(of: root)) == r(x: 0, y: 50, w: 2000, h: 1000)
+                }
+
+                it("reports container frames as the bounds of their windows") {
+                    expect(grandchild.kind.frame) == r(x: 1000, y: 50, w: 1000, h: 500)
+                    expect(child.kind.frame) == r(x: 1000, y: 50, w: 1000, h: 1000)
+                }
+
                 it("correctly resizes when a container is moved") {
                     return firstly { () -> Promise<()> in
                         // Note: in this case, `child` will end up having only one child window (b).
@@ -430,6 +632,26 @@ class TreeSpec: QuickSpec {
                         expect(e.frame).to(equal(r(x: 980, y: 50,  w: 1020, h:  343)))
                     }
                 }
+
+                it("keeps sizes summing to one over many resizes") {
+                    var rng = SeededGenerator(seed: 2509)
+                    let nodes = [aNode!, bNode!, cNode!, dNode!, eNode!]
+                    let directions: [Direction] = [.left, .right, .up, .down]
+                    var firstBadStep: Int? = nil
+                    for step in 0..<1000 where firstBadStep == nil {
+                        nodes.randomElement(using: &rng)!.kind.resize(
+                            byScreenPercentage: Float.random(in: -0.2...0.2, using: &rng),
+                            inDirection: directions.randomElement(using: &rng)!)
+                        for container in [root, child!, grandchild!] {
+                            let sum = container.children.reduce(0.0){$0 + $1.base.size}
+                            if abs(sum - 1.0) > 1e-6 {
+                                firstBadStep = step
+                            }
+                        }
+                    }
+                    expect(firstBadStep).to(beNil(),
+                                            description: "first step where sizes don't sum to one")
+                }
             }
 
             describe("Selection") {
@@ -492,6 +714,20 @@ class TreeSpec: QuickSpec {
                     expect(child.selection) == cNode.kind
                 }
 
+                it("stays with the current node when an earlier node is removed") {
+                    cNode.selectLocally()
+                    bNode.destroy()
+                    expect(child.selection) == cNode.kind
+                    expect(cNode.isSelected) == true
+                }
+
+                it("transfers to a sibling when the selected node is moved away") {
+                    cNode.selectLocally()
+                    cNode.reparent(root, at: .end)
+                    expect(child.selection) == grandchild.kind
+                    expect(cNode.isSelected) == false
+                }
+
                 it("stays with the current node when a new node is added") {
                     eNode.selectLocally()
                     expect(eNode.isSelected) == true
@@ -1,6 +1,7 @@
 import Cocoa
 import os
 import Nimble
+import PromiseKit
 import Quick
 import Swindler
 @testable import x3
@@ -28,6 +29,24 @@ class WindowManagerSpec: QuickSpec {
             _ = (a, b, c, d, e)
         }
 
+        /// Lets the main queue run for `interval`.
+        func wait(_ interval: DispatchTimeInterval) {
+            waitUntil { done in
+                DispatchQueue.main.asyncAfter(deadline: .now() + interval) {
+                    done()
+                }
+            }
+        }
+
+        /// Waits until `window`, which was just raised, is reported as focused,
+        /// then lets the ApplicationFocusedWindowChangedEvent echoing that raise
+        /// reach the window manager.
+        func settleFocus(on window: FakeWindow) {
+            expect(fakeApp.mainWindow).toEventually(equal(window))
+            expect(swindlerState.state.focusedWindow).toEventually(equal(window.window))
+            wait(.milliseconds(50))
+        }
+
         beforeSuite {
             SWINDLER_LOGGER = OSLog.disabled
             X3_LOGGER = Logger(OSLog.disabled)
@@ -53,6 +72,34 @@ class WindowManagerSpec: QuickSpec {
                     expect(b.frame).toEventually(equal(r(x: 1000, y: 50, w: 1000, h: 1000)))
                 }
 
+                it("tiles new windows after a delay when addNewWindows is set") {
+                    wm.addNewWindows = true
+                    wm.newWindowDelay = .milliseconds(10)
+                    wm.addWindow(a.window)
+                    let f = createWindowForApp(fakeApp, "F")
+                    expect(a.frame).toEventually(equal(r(x: 0,    y: 50, w: 1000, h: 1000)))
+                    expect(f.frame).toEventually(equal(r(x: 1000, y: 50, w: 1000, h: 1000)))
+                }
+
+                it("does not tile a window destroyed before the delay is up") {
+                    wm.addNewWindows = true
+                    wm.newWindowDelay = .milliseconds(200)
+                    wm.addWindow(a.window)
+                    expect(a.frame).toEventually(equal(r(x: 0, y: 50, w: 2000, h: 1000)))
+
+                    let f = createWindowForApp(fakeApp, "F")
+                    let fFrame = f.frame
+                    expect(wm.pendingNewWindows).toEventually(equal([f.window]))
+                    // Deliver the destroy the way the WindowDestroyedEvent handler does.
+                    wm.onWindowDestroyed(f.window)
+                    expect(wm.pendingNewWindows).to(beEmpty())
+
+                    wait(.milliseconds(400))
+                    expect(wm.tree.peek().find(window: f.window)).to(beNil())
+                    expect(a.frame) == r(x: 0, y: 50, w: 2000, h: 1000)
+                    expect(f.frame) == fFrame
+                }
+
                 it("raises added window") {
                     wm.addWindow(a.window)
                     expect(fakeApp.mainWindow).toEventually(equal(a))
@@ -76,6 +123,23 @@ class WindowManagerSpec: QuickSpec {
                 expect(fakeApp.mainWindow).toEventually(equal(b))
             }
 
+            it("moves focus into containers according to descentStrategy") {
+                wm.addWindow(a.window)
+                wm.addWindow(b.window)
+                wm.split(.vertical)
+                wm.addWindow(c.window)
+                wm.moveFocus(.left)
+                expect(wm.focusedWindow) == a.window
+
+                wm.moveFocus(.right)
+                expect(wm.focusedWindow) == c.window
+                wm.moveFocus(.left)
+
+                wm.descentStrategy = .first
+                wm.moveFocus(.right)
+                expect(wm.focusedWindow) == b.window
+            }
+
             it("follows external changes to window focus") {
                 wm.addWindow(a.window)
                 wm.addWindow(b.window)
@@ -89,6 +153,287 @@ class WindowManagerSpec: QuickSpec {
                 expect(fakeApp.mainWindow).toEventually(equal(b))
             }
 
+            it("keeps focus and layout when an app with no windows is activated") {
+                wm.addWindow(a.window)
+                wm.addWindow(b.window)
+                wm.addWindow(c.window)
+                expect(fakeApp.mainWindow).toEventually(equal(c))
+
+                let emptyApp = createApp(swindlerState)
+                swindlerState.frontmostApplication = emptyApp
+                expect(swindlerState.state.focusedWindow).toEventually(beNil())
+
+                expect(wm.focusedWindow) == c.window
+                expect(wm.tree.peek().root.selection?.windowNode?.window) == c.window
+                expect(a.frame).toEventually(equal(r(x: 0,    y: 50, w: 667, h: 1000)))
+                expect(b.frame).toEventually(equal(r(x: 667,  y: 50, w: 667, h: 1000)))
+                expect(c.frame).toEventually(equal(r(x: 1333, y: 50, w: 667, h: 1000)))
+            }
+
+            it("restores window frames when the system wakes") {
+                wm.addWindow(a.window)
+                wm.addWindow(b.window)
+                expect(a.frame).toEventually(equal(r(x: 0, y: 50, w: 1000, h: 1000)))
+
+                _ = a.window.frame.set(r(x: 100, y: 100, w: 300, h: 300))
+                expect(a.frame).toEventually(equal(r(x: 100, y: 100, w: 300, h: 300)))
+                wm.onSystemWoke()
+                expect(a.frame).toEventually(equal(r(x: 0,    y: 50, w: 1000, h: 1000)))
+                expect(b.frame).toEventually(equal(r(x: 1000, y: 50, w: 1000, h: 1000)))
+            }
+
+            describe("diagnose") {
+                it("lists windows that have drifted from the layout") { () -> Promise<()> in
+                    wm.addWindow(a.window)
+                    wm.addWindow(b.window)
+                    expect(a.frame).toEventually(equal(r(x: 0,    y: 50, w: 1000, h: 1000)))
+                    expect(b.frame).toEventually(equal(r(x: 1000, y: 50, w: 1000, h: 1000)))
+                    return firstly {
+                        wm.diagnose()
+                    }.done { discrepancies in
+                        expect(discrepancies).to(beEmpty())
+                    }.then { () -> Promise<[FrameDiscrepancy]> in
+                        b.frame = r(x: 1010, y: 50, w: 900, h: 1000)
+                        return wm.diagnose()
+                    }.done { discrepancies in
+                        expect(discrepancies.map { $0.window }) == [b.window]
+                        expect(discrepancies.first?.expected) == r(x: 1000, y: 50, w: 1000, h: 1000)
+                        expect(discrepancies.first?.actual) == r(x: 1010, y: 50, w: 900, h: 1000)
+                        expect(discrepancies.first?.delta) == r(x: 10, y: 0, w: -100, h: 0)
+                    }
+                }
+
+                it("ignores differences within the frame tolerances") { () -> Promise<()> in
+                    wm.frameSizeTolerance = 10
+                    wm.addWindow(a.window)
+                    wm.addWindow(b.window)
+                    expect(b.frame).toEventually(equal(r(x: 1000, y: 50, w: 1000, h: 1000)))
+                    return firstly { () -> Promise<[FrameDiscrepancy]> in
+                        b.frame = r(x: 1000, y: 50, w: 993, h: 996)
+                        return wm.diagnose()
+                    }.done { discrepancies in
+                        expect(discrepancies).to(beEmpty())
+                    }.then { () -> Promise<[FrameDiscrepancy]> in
+                        b.frame = r(x: 1005, y: 50, w: 993, h: 996)
+                        return wm.diagnose()
+                    }.done { discrepancies in
+                        expect(discrepancies.map { $0.window }) == [b.window]
+                    }
+                }
+            }
+
+            it("keeps focus on the current window when resizing") {
+                wm.addWindow(a.window)
+                wm.addWindow(b.window)
+                expect(fakeApp.mainWindow).toEventually(equal(b))
+
+                wm.resize(to: .left, screenPct: 0.1)
+                expect(a.frame).toEventually(equal(r(x: 0,   y: 50, w: 800,  h: 1000)))
+                expect(b.frame).toEventually(equal(r(x: 800, y: 50, w: 1200, h: 1000)))
+                expect(wm.focusedWindow) == b.window
+                expect(fakeApp.mainWindow) == b
+            }
+
+            it("moves the focused window along with focus when focusPullsWindow is set") {
+                wm.addWindow(a.window)
+                wm.addWindow(b.window)
+                wm.addWindow(c.window)
+                expect(fakeApp.mainWindow).toEventually(equal(c))
+
+                wm.focusPullsWindow = true
+                expect(wm.canMoveFocus(.left)) == true
+                expect(wm.canMoveFocus(.right)) == false
+                wm.moveFocus(.left)
+                expect(wm.focusedWindow) == c.window
+                expect(a.frame).toEventually(equal(r(x: 0,    y: 50, w: 667, h: 1000)))
+                expect(c.frame).toEventually(equal(r(x: 667,  y: 50, w: 667, h: 1000)))
+                expect(b.frame).toEventually(equal(r(x: 1333, y: 50, w: 667, h: 1000)))
+                expect(fakeApp.mainWindow).toEventually(equal(c))
+            }
+
+            it("does nothing when focusPullsWindow is set and the root is focused") {
+                wm.addWindow(a.window)
+                wm.addWindow(b.window)
+                wm.focusPullsWindow = true
+                wm.focusParent()
+                expect(wm.focus?.node) == wm.tree.peek().root.kind
+
+                wm.moveFocus(.right)
+                expect(wm.tree.peek().root.children.map { $0.windowNode?.window }) ==
+                    [a.window, b.window]
+            }
+
+            it("cycles focus from newest to oldest window across apps") {
+                let otherApp = createApp(swindlerState)
+                let x = createWindowForApp(otherApp, "X")
+                wm.addWindow(a.window)
+                wm.addWindow(x.window)
+                wm.addWindow(b.window)
+                expect(fakeApp.mainWindow).toEventually(equal(b))
+                expect(wm.focusedWindow) == b.window
+
+                wm.focusOlderWindow()
+                expect(wm.focusedWindow) == x.window
+                expect(otherApp.mainWindow).toEventually(equal(x))
+                wm.focusOlderWindow()
+                expect(wm.focusedWindow) == a.window
+                expect(fakeApp.mainWindow).toEventually(equal(a))
+                wm.focusOlderWindow()
+                expect(wm.focusedWindow) == b.window
+                expect(fakeApp.mainWindow).toEventually(equal(b))
+            }
+
+            it("leaves the layout alone when the focused window's app is reactivated") {
+                wm.addWindow(a.window)
+                wm.addWindow(b.window)
+                expect(fakeApp.mainWindow).toEventually(equal(b))
+                expect(a.frame).toEventually(equal(r(x: 0, y: 50, w: 1000, h: 1000)))
+                _ = a.window.frame.set(r(x: 100, y: 100, w: 300, h: 300))
+                expect(a.frame).toEventually(equal(r(x: 100, y: 100, w: 300, h: 300)))
+
+                let emptyApp = createApp(swindlerState)
+                swindlerState.frontmostApplication = emptyApp
+                expect(swindlerState.state.focusedWindow).toEventually(beNil())
+                swindlerState.frontmostApplication = fakeApp
+                expect(swindlerState.state.focusedWindow).toEventually(equal(b.window))
+
+                expect(wm.focusedWindow) == b.window
+                expect(fakeApp.mainWindow) == b
+                expect(a.frame) == r(x: 100, y: 100, w: 300, h: 300)
+            }
+
+            describe("cycleFocusWithinGroup") {
+                beforeEach {
+                    wm.addWindow(a.window)
+                    wm.addWindow(b.window)
+                    wm.split(.vertical)
+                    wm.addWindow(c.window)
+                    wm.addWindow(d.window)
+                    wm.stack(layout: .tabbed)
+                    expect(fakeApp.mainWindow).toEventually(equal(d))
+                }
+
+                it("wraps around within a tabbed group") {
+                    wm.cycleFocusWithinGroup(.right)
+                    expect(fakeApp.mainWindow).toEventually(equal(b))
+                    wm.cycleFocusWithinGroup(.right)
+                    expect(fakeApp.mainWindow).toEventually(equal(c))
+                    wm.cycleFocusWithinGroup(.left)
+                    expect(fakeApp.mainWindow).toEventually(equal(b))
+                    wm.cycleFocusWithinGroup(.left)
+                    expect(fakeApp.mainWindow).toEventually(equal(d))
+                }
+
+                it("is the only way between tabs, since moveFocus treats the group as a unit") {
+                    wm.cycleFocusWithinGroup(.right)
+                    expect(fakeApp.mainWindow).toEventually(equal(b))
+
+                    // b isn't the last tab, but nothing lies right of the group.
+                    expect(wm.canMoveFocus(.right)) == false
+                    wm.moveFocus(.right)
+                    expect(wm.focusedWindow) == b.window
+
+                    wm.moveFocus(.left)
+                    expect(fakeApp.mainWindow).toEventually(equal(a))
+                    wm.moveFocus(.right)
+                    expect(fakeApp.mainWindow).toEventually(equal(b))
+                    wm.cycleFocusWithinGroup(.left)
+                    expect(fakeApp.mainWindow).toEventually(equal(d))
+                }
+
+                it("does nothing outside of a group") {
+                    fakeApp.mainWindow = a
+                    expect(wm.focusedWindow).toEventually(equal(a.window))
+                    wm.cycleFocusWithinGroup(.right)
+                    expect(wm.focusedWindow) == a.window
+                }
+            }
+
+            describe("focusVisual") {
+                var bNode: WindowNode!
+                beforeEach {
+                    wm.tree.peek().root
+                        .makeContainer(layout: .vertical) { n in
+                            n.makeWindow(a.window)
+                             .makeWindow(b.window) { bNode = $0 }
+                        }
+                        .makeContainer(layout: .vertical) { n in
+                            n.makeWindow(c.window)
+                             .makeWindow(d.window)
+                             .makeWindow(e.window)
+                        }
+                    wm.tree.peek().find(window: c.window)!.selectGlobally()
+                    bNode.selectGlobally()
+                    wm.focus = Crawler(at: bNode)
+                }
+
+                it("picks the visually adjacent window instead of the selected one") {
+                    wm.moveFocus(.right)
+                    expect(wm.focusedWindow) == c.window
+
+                    bNode.selectGlobally()
+                    wm.focus = Crawler(at: bNode)
+                    wm.focusVisual(.right)
+                    expect(wm.focusedWindow) == e.window
+                    expect(fakeApp.mainWindow).toEventually(equal(e))
+                }
+
+                it("moves within a column") {
+                    wm.focusVisual(.up)
+                    expect(wm.focusedWindow) == a.window
+                    wm.focusVisual(.up)  // no-op
+                    expect(wm.focusedWindow) == a.window
+                }
+
+                it("picks the showing tab of a tabbed neighbor") {
+                    let dNode = wm.tree.peek().find(window: d.window)!
+                    dNode.parent!.layout = .tabbed
+                    dNode.selectLocally()
+                    wm.focusVisual(.right)
+                    expect(wm.focusedWindow) == d.window
+                    expect(fakeApp.mainWindow).toEventually(equal(d))
+                }
+            }
+
+            it("focuses the biggest and smallest windows") {
+                wm.addWindow(a.window)
+                wm.addWindow(b.window)
+                wm.split(.vertical)
+                wm.addWindow(c.window)
+                wm.resize(to: .up, screenPct: 0.1)
+                expect(b.frame).toEventually(equal(r(x: 1000, y: 650, w: 1000, h: 400)))
+                expect(c.frame).toEventually(equal(r(x: 1000, y:  50, w: 1000, h: 600)))
+
+                wm.focusBiggest()
+                expect(wm.focusedWindow) == a.window
+                expect(fakeApp.mainWindow).toEventually(equal(a))
+
+                wm.focusSmallest()
+                expect(wm.focusedWindow) == b.window
+                expect(fakeApp.mainWindow).toEventually(equal(b))
+            }
+
+            it("ignores hidden tabs when focusing the biggest window") {
+                var aNode, cNode: WindowNode!
+                wm.tree.peek().root
+                    .makeContainer(layout: .tabbed) { n in
+                        n.makeWindow(b.window)
+                         .makeWindow(c.window) { cNode = $0 }
+                    }
+                    .makeContainer(layout: .vertical) { n in
+                        n.makeWindow(a.window) { aNode = $0 }
+                         .makeWindow(d.window)
+                    }
+                cNode.selectLocally()
+                aNode.selectGlobally()
+                wm.focus = Crawler(at: aNode)
+
+                // Both tabs fill the left half, but only c is showing.
+                wm.focusBiggest()
+                expect(wm.focusedWindow) == c.window
+                expect(fakeApp.mainWindow).toEventually(equal(c))
+            }
+
             it("allows moving up and down the tree") {
                 wm.addWindow(a.window)
                 wm.addWindow(b.window)
@@ -107,6 +452,34 @@ class WindowManagerSpec: QuickSpec {
                 expect(c.frame).toEventually(equal(r(x:  667, y:  50, w:  667, h: 1000)))
             }
 
+            describe("moveToMark") {
+                var aNode, bNode, cNode: WindowNode!
+                beforeEach {
+                    aNode = wm.addWindowReturningNode(a.window)!
+                    bNode = wm.addWindowReturningNode(b.window)!
+                    wm.split(.vertical)
+                    cNode = wm.addWindowReturningNode(c.window)!
+                }
+
+                it("moves the focused window into the marked container") {
+                    wm.markContainer()
+                    wm.moveFocus(.left)
+                    expect(wm.focusedWindow) == a.window
+                    wm.moveToMark()
+                    expect(aNode.parent) == cNode.parent
+                    expect(cNode.parent?.children) == [bNode.kind, cNode.kind, aNode.kind]
+                }
+
+                it("does nothing once the marked container is gone") {
+                    wm.markContainer()
+                    bNode.destroy()
+                    cNode.destroy()
+                    wm.focus = Crawler(at: aNode)
+                    wm.moveToMark()
+                    expect(aNode.parent) == wm.tree.peek().root
+                }
+            }
+
             describe("split") {
                 it("with no windows, sets the direction of the root") {
                     wm.split(.vertical)
@@ -155,6 +528,130 @@ class WindowManagerSpec: QuickSpec {
                     expect(c.frame).toEventually(equal(r(x: 1000, y:  50, w: 1000, h:  500)))
                     expect(bNode.parent?.parent).to(equal(wm.tree.peek().root))
                 }
+
+                context("on the only child of a container") {
+                    var bNode: WindowNode!
+                    beforeEach {
+                        wm.addWindow(a.window)
+                        bNode = wm.addWindowReturningNode(b.window)!
+                        wm.split(.vertical)
+                    }
+
+                    it("changes the layout of the existing container by default") {
+                        let parent = bNode.parent!
+                        wm.split(.horizontal)
+                        expect(bNode.parent) == parent
+                        expect(parent.layout) == .horizontal
+                        expect(parent.parent) == wm.tree.peek().root
+                    }
+
+                    it("creates a new container when splitAlwaysNests is set") {
+                        wm.splitAlwaysNests = true
+                        let parent = bNode.parent!
+                        wm.split(.horizontal)
+                        expect(bNode.parent?.parent) == parent
+                        expect(bNode.parent?.layout) == .horizontal
+                        expect(parent.layout) == .vertical
+                    }
+                }
+            }
+
+            describe("splitAndAddNext") {
+                it("places the next created window in the new container") {
+                    wm.newWindowDelay = .milliseconds(10)
+                    wm.addWindow(a.window)
+                    let bNode = wm.addWindowReturningNode(b.window)!
+                    settleFocus(on: b)
+                    wm.splitAndAddNext(.vertical)
+                    let f = createWindowForApp(fakeApp, "F")
+                    expect(wm.focusedWindow).toEventually(equal(f.window))
+                    expect(a.frame).toEventually(equal(r(x: 0,    y:  50, w: 1000, h: 1000)))
+                    expect(b.frame).toEventually(equal(r(x: 1000, y: 550, w: 1000, h:  500)))
+                    expect(f.frame).toEventually(equal(r(x: 1000, y:  50, w: 1000, h:  500)))
+                    expect(wm.tree.peek().find(window: f.window)?.parent) == bNode.parent
+                }
+
+                it("uses the new container even if focus moves while the window is pending") {
+                    wm.newWindowDelay = .milliseconds(200)
+                    wm.addWindow(a.window)
+                    let bNode = wm.addWindowReturningNode(b.window)!
+                    settleFocus(on: b)
+                    wm.splitAndAddNext(.vertical)
+                    let f = createWindowForApp(fakeApp, "F")
+                    expect(wm.pendingNewWindows).toEventually(equal([f.window]))
+                    wm.moveFocus(.left)
+                    expect(wm.tree.peek().find(window: f.window)?.parent)
+                        .toEventually(equal(bNode.parent))
+                }
+
+                it("is cancelled when focus moves before a window is created") {
+                    wm.newWindowDelay = .milliseconds(10)
+                    wm.addWindow(a.window)
+                    wm.addWindow(b.window)
+                    settleFocus(on: b)
+                    wm.splitAndAddNext(.vertical)
+                    wm.moveFocus(.left)
+                    let f = createWindowForApp(fakeApp, "F")
+                    wait(.milliseconds(100))
+                    expect(wm.tree.peek().find(window: f.window)).to(beNil())
+                }
+
+                it("expires after addNextWindowTimeout") {
+                    wm.newWindowDelay = .milliseconds(10)
+                    wm.addNextWindowTimeout = .milliseconds(50)
+                    wm.addWindow(a.window)
+                    wm.addWindow(b.window)
+                    settleFocus(on: b)
+                    wm.splitAndAddNext(.vertical)
+                    wait(.milliseconds(100))
+                    let f = createWindowForApp(fakeApp, "F")
+                    wait(.milliseconds(100))
+                    expect(wm.tree.peek().find(window: f.window)).to(beNil())
+                }
+            }
+
+            describe("maxNestingDepth") {
+                it("stops split from nesting deeper than the limit") {
+                    wm.maxNestingDepth = 2
+                    wm.splitAlwaysNests = true
+                    wm.addWindow(a.window)
+                    let bNode = wm.addWindowReturningNode(b.window)!
+                    expect(bNode.depth) == 1
+
+                    wm.split(.vertical)
+                    expect(bNode.depth) == 2
+                    let parent = bNode.parent!
+
+                    wm.split(.horizontal)
+                    expect(bNode.depth) == 2
+                    expect(bNode.parent) == parent
+                    expect(parent.layout) == .vertical
+                }
+
+                it("still allows changing the layout of an only child's container") {
+                    wm.maxNestingDepth = 2
+                    wm.addWindow(a.window)
+                    let bNode = wm.addWindowReturningNode(b.window)!
+                    wm.split(.vertical)
+                    wm.split(.horizontal)
+                    expect(bNode.depth) == 2
+                    expect(bNode.parent?.layout) == .horizontal
+                }
+
+                it("stops split on a focused container whose windows are at the limit") {
+                    wm.maxNestingDepth = 2
+                    wm.addWindow(a.window)
+                    let bNode = wm.addWindowReturningNode(b.window)!
+                    wm.split(.vertical)
+                    wm.addWindow(c.window)
+                    let container = bNode.parent!
+                    expect(container.height) == 1
+
+                    wm.focusParent()
+                    wm.split(.horizontal)
+                    expect(bNode.depth) == 2
+                    expect(container.parent) == wm.tree.peek().root
+                }
             }
 
             describe("stack") {
@@ -189,6 +686,174 @@ class WindowManagerSpec: QuickSpec {
 
                 testStack(to: .stacked)
                 testStack(to: .tabbed)
+
+                it("unstacks a focused stacked container instead of its parent") {
+                    wm.addWindow(a.window)
+                    wm.addWindow(b.window)
+                    wm.split(.vertical)
+                    wm.addWindow(c.window)
+                    wm.stack(layout: .stacked)
+                    wm.moveFocus(.left)
+                    expect(wm.focusedWindow) == a.window
+                    wm.stack(layout: .tabbed)
+                    wm.cycleFocusWithinGroup(.right)
+                    expect(wm.focusedWindow) == c.window
+
+                    wm.focusParent()
+                    wm.unstack()
+                    let tree = wm.tree.peek()
+                    expect(tree.find(window: c.window)?.parent?.layout) == .vertical
+                    expect(tree.root.layout) == .tabbed
+                }
+            }
+
+            describe("maxChildren") {
+                it("stacks a container once it has too many children") {
+                    wm.tree.peek().root.wmData.maxChildren = 2
+                    wm.addWindow(a.window)
+                    wm.addWindow(b.window)
+                    expect(a.frame).toEventually(equal(r(x: 0,    y: 50, w: 1000, h: 1000)))
+                    expect(b.frame).toEventually(equal(r(x: 1000, y: 50, w: 1000, h: 1000)))
+                    expect(wm.tree.peek().root.layout) == .horizontal
+
+                    wm.addWindow(c.window)
+                    expect(wm.tree.peek().root.layout) == .stacked
+                    expect(a.frame).toEventually(equal(r(x: 0, y: 50, w: 2000, h: 1000)))
+                    expect(b.frame).toEventually(equal(r(x: 0, y: 50, w: 2000, h: 1000)))
+                    expect(c.frame).toEventually(equal(r(x: 0, y: 50, w: 2000, h: 1000)))
+
+                    wm.unstack()
+                    expect(wm.tree.peek().root.layout) == .horizontal
+                }
+            }
+
+            describe("resetLayout") {
+                it("flattens a nested, stacked tree into an even horizontal split") {
+                    wm.addWindow(a.window)
+                    wm.addWindow(b.window)
+                    wm.split(.vertical)
+                    wm.addWindow(c.window)
+                    wm.split(.horizontal)
+                    wm.splitAlwaysNests = true
+                    wm.split(.vertical)
+                    wm.addWindow(d.window)
+                    wm.stack(layout: .tabbed)
+                    expect(wm.focusedWindow) == d.window
+
+                    wm.resetLayout()
+                    let root = wm.tree.peek().root
+                    expect(root.layout) == .horizontal
+                    expect(root.children.map { $0.windowNode?.window }) ==
+                        [a.window, b.window, c.window, d.window]
+                    expect(a.frame).toEventually(equal(r(x: 0,    y: 50, w: 500, h: 1000)))
+                    expect(b.frame).toEventually(equal(r(x: 500,  y: 50, w: 500, h: 1000)))
+                    expect(c.frame).toEventually(equal(r(x: 1000, y: 50, w: 500, h: 1000)))
+                    expect(d.frame).toEventually(equal(r(x: 1500, y: 50, w: 500, h: 1000)))
+                    expect(wm.focusedWindow) == d.window
+                    expect(root.selection?.windowNode?.window) == d.window
+                }
+
+                it("keeps the root's maxChildren") {
+                    wm.tree.peek().root.wmData.maxChildren = 5
+                    wm.addWindow(a.window)
+                    wm.addWindow(b.window)
+                    wm.stack(layout: .tabbed)
+
+                    wm.resetLayout()
+                    let root = wm.tree.peek().root
+                    expect(root.layout) == .horizontal
+                    expect(root.wmData.unstackLayout).to(beNil())
+                    expect(root.wmData.maxChildren) == 5
+                }
+
+                it("stacks the root if it has more windows than maxChildren") {
+                    wm.tree.peek().root.wmData.maxChildren = 2
+                    wm.addWindow(a.window)
+                    wm.addWindow(b.window)
+                    wm.addWindow(c.window)
+                    expect(wm.tree.peek().root.layout) == .stacked
+
+                    wm.resetLayout()
+                    let root = wm.tree.peek().root
+                    expect(root.layout) == .stacked
+                    expect(root.wmData.unstackLayout) == .horizontal
+                    expect(a.frame).toEventually(equal(r(x: 0, y: 50, w: 2000, h: 1000)))
+                    expect(c.frame).toEventually(equal(r(x: 0, y: 50, w: 2000, h: 1000)))
+
+                    wm.unstack()
+                    expect(c.frame).toEventually(equal(r(x: 1333, y: 50, w: 667, h: 1000)))
+                }
+            }
+
+            describe("flipAll") {
+                it("flips every container, transposing the layout") {
+                    wm.addWindow(a.window)
+                    wm.addWindow(b.window)
+                    wm.split(.vertical)
+                    wm.addWindow(c.window)
+                    expect(a.frame).toEventually(equal(r(x: 0,    y:  50, w: 1000, h: 1000)))
+                    expect(b.frame).toEventually(equal(r(x: 1000, y: 550, w: 1000, h:  500)))
+                    expect(c.frame).toEventually(equal(r(x: 1000, y:  50, w: 1000, h:  500)))
+
+                    wm.flipAll()
+                    expect(wm.tree.peek().root.layout) == .vertical
+                    expect(a.frame).toEventually(equal(r(x: 0,    y: 550, w: 2000, h: 500)))
+                    expect(b.frame).toEventually(equal(r(x: 0,    y:  50, w: 1000, h: 500)))
+                    expect(c.frame).toEventually(equal(r(x: 1000, y:  50, w: 1000, h: 500)))
+                    expect(wm.focusedWindow) == c.window
+                }
+            }
+
+            describe("cycleLayout") {
+                it("applies each layout in order and wraps around") {
+                    wm.layoutCycle = [.vertical, .tabbed, .horizontal]
+                    wm.addWindow(a.window)
+                    wm.addWindow(b.window)
+                    expect(a.frame).toEventually(equal(r(x: 0,    y: 50, w: 1000, h: 1000)))
+                    expect(b.frame).toEventually(equal(r(x: 1000, y: 50, w: 1000, h: 1000)))
+
+                    wm.cycleLayout()
+                    expect(a.frame).toEventually(equal(r(x: 0, y: 550, w: 2000, h: 500)))
+                    expect(b.frame).toEventually(equal(r(x: 0, y:  50, w: 2000, h: 500)))
+
+                    wm.cycleLayout()
+                    expect(a.frame).toEventually(equal(r(x: 0, y: 50, w: 2000, h: 1000)))
+                    expect(b.frame).toEventually(equal(r(x: 0, y: 50, w: 2000, h: 1000)))
+
+                    wm.cycleLayout()
+                    expect(a.frame).toEventually(equal(r(x: 0,    y: 50, w: 1000, h: 1000)))
+                    expect(b.frame).toEventually(equal(r(x: 1000, y: 50, w: 1000, h: 1000)))
+
+                    wm.cycleLayout()
+                    expect(a.frame).toEventually(equal(r(x: 0, y: 550, w: 2000, h: 500)))
+                    expect(b.frame).toEventually(equal(r(x: 0, y:  50, w: 2000, h: 500)))
+                }
+            }
+
+            describe("layoutSnapshot") {
+                it("describes the layout produced by a sequence of commands") {
+                    wm.addWindow(a.window)
+                    wm.addWindow(b.window)
+                    wm.split(.vertical)
+                    wm.addWindow(c.window)
+                    let before = wm.layoutSnapshot()
+                    expect(before.diff(LayoutSnapshot([
+                        (a.window, r(x: 0,    y:  50, w: 1000, h: 1000)),
+                        (b.window, r(x: 1000, y: 550, w: 1000, h:  500)),
+                        (c.window, r(x: 1000, y:  50, w: 1000, h:  500)),
+                    ]))).to(beEmpty())
+
+                    wm.resize(to: .up, screenPct: 0.1)
+                    expect(wm.layoutSnapshot().diff(before)) == [b.window, c.window]
+                    expect(wm.layoutSnapshot().diff(LayoutSnapshot([]))) == [a.window, b.window, c.window]
+                }
+            }
+
+            it("leaves the reserved space it was created with empty") {
+                wm = WindowManager(state: swindlerState.state,
+                                   reserved: NSEdgeInsets(top: 20, left: 100, bottom: 0, right: 0))
+                wm.addWindow(a.window)
+                expect(a.frame).toEventually(equal(r(x: 100, y: 50, w: 1900, h: 980)))
             }
 
             describe("recovery") {
@@ -207,6 +872,99 @@ class WindowManagerSpec: QuickSpec {
 
                     expect(fakeApp.mainWindow).toEventually(equal(b))
                 }
+
+                it("keeps focusPullsWindow") {
+                    wm.addWindow(a.window)
+                    wm.focusPullsWindow = true
+
+                    let data = try! wm.serialize()
+                    wm = try! WindowManager.recover(from: data, state: swindlerState.state)
+                    expect(wm.focusPullsWindow) == true
+                }
+
+                it("keeps reserved space") {
+                    wm.reserved = NSEdgeInsets(top: 20, left: 100, bottom: 0, right: 0)
+                    wm.addWindow(a.window)
+
+                    let data = try! wm.serialize()
+                    wm = try! WindowManager.recover(from: data, state: swindlerState.state)
+                    expect(wm.reserved.top) == 20
+                    expect(wm.reserved.left) == 100
+                    expect(a.frame).toEventually(equal(r(x: 100, y: 50, w: 1900, h: 980)))
+                }
+
+                it("lets the caller override reserved space") {
+                    wm.reserved = NSEdgeInsets(top: 20, left: 100, bottom: 0, right: 0)
+                    wm.addWindow(a.window)
+
+                    let data = try! wm.serialize()
+                    wm = try! WindowManager.recover(
+                        from: data, state: swindlerState.state,
+                        reserved: NSEdgeInsets(top: 0, left: 0, bottom: 0, right: 50))
+                    expect(wm.reserved.left) == 0
+                    expect(a.frame).toEventually(equal(r(x: 0, y: 50, w: 1950, h: 1000)))
+                }
+
+                it("keeps size locks") {
+                    wm.addWindow(a.window)
+                    wm.addWindow(b.window)
+                    expect(fakeApp.mainWindow).toEventually(equal(b))
+                    wm.toggleSizeLock()
+
+                    let data = try! wm.serialize()
+                    wm = try! WindowManager.recover(from: data, state: swindlerState.state)
+
+                    let tree = wm.tree.peek()
+                    expect(tree.find(window: a.window)?.sizeLocked) == false
+                    expect(tree.find(window: b.window)?.sizeLocked) == true
+                }
+            }
+        }
+
+        context("with two screens") {
+            var left, right: FakeScreen!
+
+            beforeEach {
+                left = FakeScreen(frame: CGRect(x: 0, y: 0, width: 2000, height: 1060),
+                                  menuBarHeight: 10,
+                                  dockHeight: 50)
+                right = FakeScreen(frame: CGRect(x: 2000, y: 0, width: 1000, height: 1060),
+                                   menuBarHeight: 10,
+                                   dockHeight: 50)
+                setup(screens: [left, right])
+            }
+
+            describe("screen(containingMostOf:)") {
+                it("picks the screen with the larger part of a straddling frame") {
+                    let state = swindlerState.state
+                    let frame = r(x: 1800, y: 100, w: 600, h: 400)
+                    expect(state.screen(containingMostOf: frame)?.frame) == right.screen.frame
+                    let otherFrame = r(x: 1500, y: 100, w: 600, h: 400)
+                    expect(state.screen(containingMostOf: otherFrame)?.frame) == left.screen.frame
+                }
+
+                it("is nil for a frame off all screens") {
+                    let frame = r(x: 5000, y: 100, w: 600, h: 400)
+                    expect(swindlerState.state.screen(containingMostOf: frame)).to(beNil())
+                }
+            }
+
+            it("tiles on the screen it was given") {
+                let wm = WindowManager(state: swindlerState.state, screen: left.screen)
+                wm.addWindow(a.window)
+                expect(a.frame).toEventually(equal(left.screen.applicationFrame))
+            }
+
+            it("keeps tiling on the same screen after recovery") {
+                var wm = WindowManager(state: swindlerState.state, screen: left.screen)
+                wm.addWindow(a.window)
+                wm.addWindow(b.window)
+                expect(fakeApp.mainWindow).toEventually(equal(b))
+                let data = try! wm.serialize()
+                wm = try! WindowManager.recover(from: data,
+                                                state: swindlerState.state,
+                                                screen: left.screen)
+                expect(wm.tree.peek().screen.frame) == left.screen.frame
             }
         }
     }